@@ -0,0 +1,348 @@
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{Map, Value};
+
+use crate::models::ModelsConfig;
+use crate::ollama::OllamaGenerationStats;
+
+// Typed `/api/chat` payloads. Requests from the webview are parsed and validated
+// here so malformed input fails before it reaches Ollama.
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ChatRole {
+    System,
+    User,
+    Assistant,
+    Tool,
+    // Only produced by deserialization; rejected by `ChatRequest::validate`.
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ToolCallFunction {
+    pub name: String,
+    #[serde(default)]
+    pub arguments: Value,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ToolCall {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub function: ToolCallFunction,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChatMessage {
+    pub role: ChatRole,
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub content: String,
+    #[serde(
+        default,
+        deserialize_with = "null_as_default",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub images: Vec<String>,
+    #[serde(
+        default,
+        deserialize_with = "null_as_default",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub tool_calls: Vec<ToolCall>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thinking: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_name: Option<String>,
+}
+
+impl ChatMessage {
+    pub fn new(role: ChatRole, content: impl Into<String>) -> Self {
+        Self {
+            role,
+            content: content.into(),
+            images: Vec::new(),
+            tool_calls: Vec::new(),
+            thinking: None,
+            tool_name: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ChatOptions {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_p: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repeat_penalty: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub num_ctx: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub num_predict: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub num_thread: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stop: Option<Vec<String>>,
+    // Less common Ollama options are forwarded untouched.
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChatRequest {
+    // May be omitted when `models.default_chat_model` is configured.
+    #[serde(default)]
+    pub model: String,
+    pub messages: Vec<ChatMessage>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tools: Option<Vec<Value>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub options: Option<ChatOptions>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stream: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keep_alive: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub think: Option<Value>,
+}
+
+/// What the context manager did to a request, returned with the response.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ContextReport {
+    pub context_length: u64,
+    pub estimated_tokens: u64,
+    pub dropped_messages: usize,
+    pub dropped_tokens: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChatResponse {
+    #[serde(default)]
+    pub model: String,
+    #[serde(default)]
+    pub created_at: Option<String>,
+    pub message: ChatMessage,
+    #[serde(default)]
+    pub done: bool,
+    #[serde(flatten)]
+    pub stats: OllamaGenerationStats,
+    // Set when older turns were trimmed to fit the context window.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context: Option<ContextReport>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ValidationIssue {
+    pub field: String,
+    pub message: String,
+}
+
+/// Error returned by chat commands; `message` is always present for the UI.
+#[derive(Debug, Serialize, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ChatError {
+    Validation {
+        message: String,
+        issues: Vec<ValidationIssue>,
+    },
+    Request {
+        message: String,
+    },
+    // Structured output that still failed schema validation after retries.
+    SchemaMismatch {
+        message: String,
+        issues: Vec<ValidationIssue>,
+    },
+}
+
+impl ChatError {
+    fn validation(issues: Vec<ValidationIssue>) -> Self {
+        let message = issues
+            .iter()
+            .map(|issue| format!("{}: {}", issue.field, issue.message))
+            .collect::<Vec<_>>()
+            .join("; ");
+        Self::Validation {
+            message: format!("Invalid chat request: {message}"),
+            issues,
+        }
+    }
+}
+
+impl From<String> for ChatError {
+    fn from(message: String) -> Self {
+        Self::Request { message }
+    }
+}
+
+impl std::fmt::Display for ChatError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Validation { message, .. }
+            | Self::Request { message }
+            | Self::SchemaMismatch { message, .. } => f.write_str(message),
+        }
+    }
+}
+
+fn null_as_default<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Default + Deserialize<'de>,
+{
+    Ok(Option::<T>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+fn check_range(
+    issues: &mut Vec<ValidationIssue>,
+    field: &str,
+    value: Option<f64>,
+    min: f64,
+    max: f64,
+) {
+    let Some(value) = value else {
+        return;
+    };
+    if !value.is_finite() || value < min || value > max {
+        issues.push(ValidationIssue {
+            field: format!("options.{field}"),
+            message: format!("must be between {min} and {max}, got {value}"),
+        });
+    }
+}
+
+impl ChatRequest {
+    /// Parse an untyped webview payload, reporting shape errors as validation issues.
+    /// Configured model defaults and presets are merged in before validation.
+    pub fn from_value(value: Value, models: &ModelsConfig) -> Result<Self, ChatError> {
+        let mut request = Self::parse(value)?;
+        request.apply_presets(models);
+        request.validate()?;
+        Ok(request)
+    }
+
+    fn parse(value: Value) -> Result<Self, ChatError> {
+        serde_json::from_value::<ChatRequest>(value).map_err(|err| {
+            ChatError::validation(vec![ValidationIssue {
+                field: "request".to_string(),
+                message: err.to_string(),
+            }])
+        })
+    }
+
+    /// Merges config presets into the request; anything the request sets wins.
+    pub fn apply_presets(&mut self, models: &ModelsConfig) {
+        if self.model.trim().is_empty() {
+            if let Some(model) = &models.default_chat_model {
+                self.model = model.clone();
+            }
+        }
+        let Some(preset) = models.preset_for(&self.model) else {
+            return;
+        };
+        let options = self.options.get_or_insert_with(ChatOptions::default);
+        options.temperature = options.temperature.or(preset.temperature);
+        options.top_p = options.top_p.or(preset.top_p);
+        options.num_ctx = options.num_ctx.or(preset.num_ctx);
+        options.num_thread = options.num_thread.or(preset.num_thread);
+        options.seed = options.seed.or(preset.seed);
+        if options.stop.is_none() {
+            options.stop = preset.stop.clone();
+        }
+        if self.think.is_none() {
+            self.think = preset.think.clone();
+        }
+        let has_system = self
+            .messages
+            .iter()
+            .any(|message| message.role == ChatRole::System);
+        if let Some(prompt) = preset.system_prompt.as_ref().filter(|_| !has_system) {
+            self.messages
+                .insert(0, ChatMessage::new(ChatRole::System, prompt.clone()));
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), ChatError> {
+        let mut issues = Vec::new();
+        if self.model.trim().is_empty() {
+            issues.push(ValidationIssue {
+                field: "model".to_string(),
+                message: "is required".to_string(),
+            });
+        }
+        if self.messages.is_empty() {
+            issues.push(ValidationIssue {
+                field: "messages".to_string(),
+                message: "must contain at least one message".to_string(),
+            });
+        }
+        for (index, message) in self.messages.iter().enumerate() {
+            if message.role == ChatRole::Unknown {
+                issues.push(ValidationIssue {
+                    field: format!("messages[{index}].role"),
+                    message: "must be one of system, user, assistant, tool".to_string(),
+                });
+            }
+            if !message.images.is_empty() && message.role != ChatRole::User {
+                issues.push(ValidationIssue {
+                    field: format!("messages[{index}].images"),
+                    message: "images are only allowed on user messages".to_string(),
+                });
+            }
+            if !message.tool_calls.is_empty() && message.role != ChatRole::Assistant {
+                issues.push(ValidationIssue {
+                    field: format!("messages[{index}].tool_calls"),
+                    message: "tool calls are only allowed on assistant messages".to_string(),
+                });
+            }
+        }
+        if let Some(options) = &self.options {
+            check_range(&mut issues, "temperature", options.temperature, 0.0, 2.0);
+            check_range(&mut issues, "top_p", options.top_p, 0.0, 1.0);
+            check_range(&mut issues, "min_p", options.min_p, 0.0, 1.0);
+            check_range(
+                &mut issues,
+                "repeat_penalty",
+                options.repeat_penalty,
+                0.0,
+                10.0,
+            );
+            check_range(
+                &mut issues,
+                "num_ctx",
+                options.num_ctx.map(f64::from),
+                1.0,
+                1_048_576.0,
+            );
+            check_range(
+                &mut issues,
+                "num_predict",
+                options.num_predict.map(f64::from),
+                -2.0,
+                f64::from(i32::MAX),
+            );
+            check_range(
+                &mut issues,
+                "num_thread",
+                options.num_thread.map(f64::from),
+                1.0,
+                1024.0,
+            );
+        }
+        if issues.is_empty() {
+            Ok(())
+        } else {
+            Err(ChatError::validation(issues))
+        }
+    }
+}
@@ -0,0 +1,32 @@
+// Error reporting shared by every HTTP backend. `service` names the server in
+// messages, e.g. "Ollama", "SearxNG" or a host name.
+
+pub fn describe_reqwest_error(err: &reqwest::Error, service: &str) -> String {
+    // Normalize common failure modes for the UI.
+    if err.is_connect() && err.is_timeout() {
+        return format!("timeout while connecting to {service}");
+    }
+    if err.is_connect() {
+        return format!("connection refused by {service}");
+    }
+    if err.is_timeout() {
+        return format!("timed out waiting for {service} to respond");
+    }
+    format!("request error: {err}")
+}
+
+/// Passes successful responses through; otherwise logs and returns the status and body.
+pub async fn ensure_success(
+    response: reqwest::Response,
+    service: &str,
+    context: &str,
+) -> Result<reqwest::Response, String> {
+    if response.status().is_success() {
+        return Ok(response);
+    }
+    let status = response.status();
+    let body = response.text().await.unwrap_or_default();
+    let detail = format!("non-200 from {service}: {status} {body}");
+    eprintln!("{context} failed: {detail}");
+    Err(detail)
+}
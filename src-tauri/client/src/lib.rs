@@ -0,0 +1,16 @@
+// HTTP clients for Ollama and OpenAI-compatible servers, the stream pump, and
+// the request/response types they share. Nothing here depends on Tauri, so
+// `cargo test -p copilot-client` runs on machines without the webview stack.
+
+pub mod chat;
+pub mod http;
+pub mod metrics;
+#[cfg(test)]
+mod mock_ollama;
+pub mod models;
+pub mod ollama;
+pub mod openai;
+pub mod provider;
+pub mod search;
+pub mod stream;
+pub mod thinking;
@@ -0,0 +1,79 @@
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::ollama::OllamaGenerationStats;
+
+/// Metrics for one generation. Ollama reports durations in nanoseconds.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GenerationMetrics {
+    pub model: String,
+    #[serde(default)]
+    pub created_at: u64,
+    pub prompt_tokens: Option<u64>,
+    pub completion_tokens: Option<u64>,
+    pub load_ms: Option<f64>,
+    pub prompt_eval_ms: Option<f64>,
+    pub eval_ms: Option<f64>,
+    pub total_ms: Option<f64>,
+    pub tokens_per_second: Option<f64>,
+    pub prompt_tokens_per_second: Option<f64>,
+    pub time_to_first_token_ms: Option<f64>,
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn nanos_to_ms(nanos: Option<u64>) -> Option<f64> {
+    nanos.map(|value| value as f64 / 1_000_000.0)
+}
+
+fn rate(tokens: Option<u64>, millis: Option<f64>) -> Option<f64> {
+    match (tokens, millis) {
+        (Some(tokens), Some(millis)) if tokens > 0 && millis > 0.0 => {
+            Some(tokens as f64 * 1000.0 / millis)
+        }
+        _ => None,
+    }
+}
+
+impl GenerationMetrics {
+    /// `first_token` and `elapsed` are client-side timings, measured from when
+    /// the request was sent; they fill in for providers that omit durations.
+    pub fn from_stats(
+        model: &str,
+        stats: &OllamaGenerationStats,
+        first_token: Option<Duration>,
+        elapsed: Duration,
+    ) -> Self {
+        let load_ms = nanos_to_ms(stats.load_duration);
+        let prompt_eval_ms = nanos_to_ms(stats.prompt_eval_duration);
+        let total_ms = nanos_to_ms(stats.total_duration).or(Some(elapsed.as_secs_f64() * 1000.0));
+        let ttft_ms = first_token
+            .map(|duration| duration.as_secs_f64() * 1000.0)
+            .or_else(|| match (load_ms, prompt_eval_ms) {
+                (None, None) => None,
+                (load, prompt) => Some(load.unwrap_or(0.0) + prompt.unwrap_or(0.0)),
+            });
+        let eval_ms = nanos_to_ms(stats.eval_duration).or_else(|| {
+            let first = first_token?;
+            Some(elapsed.saturating_sub(first).as_secs_f64() * 1000.0)
+        });
+        Self {
+            model: model.to_string(),
+            created_at: now_millis(),
+            prompt_tokens: stats.prompt_eval_count,
+            completion_tokens: stats.eval_count,
+            load_ms,
+            prompt_eval_ms,
+            eval_ms,
+            total_ms,
+            tokens_per_second: rate(stats.eval_count, eval_ms),
+            prompt_tokens_per_second: rate(stats.prompt_eval_count, prompt_eval_ms),
+            time_to_first_token_ms: ttft_ms,
+        }
+    }
+}
@@ -0,0 +1,177 @@
+// In-process stand-in for the Ollama HTTP API, used by the tests. Each route
+// replays a scripted response; bodies are written as separate HTTP chunks with
+// optional delays so tests can control exactly how bytes reach the client.
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+#[derive(Debug, Clone)]
+pub struct MockResponse {
+    status: u16,
+    content_type: String,
+    // Held before the status line, to exercise request timeouts.
+    header_delay: Duration,
+    // Each piece is flushed as its own chunk after its delay.
+    parts: Vec<(Duration, Vec<u8>)>,
+}
+
+impl MockResponse {
+    pub fn json(status: u16, body: &str) -> Self {
+        Self {
+            status,
+            content_type: "application/json".into(),
+            header_delay: Duration::ZERO,
+            parts: vec![(Duration::ZERO, body.as_bytes().to_vec())],
+        }
+    }
+
+    /// Streams `parts` exactly as given; split or join lines to shape the chunks.
+    pub fn ndjson<T: AsRef<[u8]>>(parts: &[T]) -> Self {
+        Self {
+            status: 200,
+            content_type: "application/x-ndjson".into(),
+            header_delay: Duration::ZERO,
+            parts: parts
+                .iter()
+                .map(|part| (Duration::ZERO, part.as_ref().to_vec()))
+                .collect(),
+        }
+    }
+
+    /// Waits `delay` before each body chunk.
+    pub fn with_chunk_delay(mut self, delay: Duration) -> Self {
+        for part in &mut self.parts {
+            part.0 = delay;
+        }
+        self
+    }
+
+    /// Holds back body chunk `index` (and everything after it) for `delay`.
+    pub fn with_stall_before(mut self, index: usize, delay: Duration) -> Self {
+        self.parts[index].0 = delay;
+        self
+    }
+
+    pub fn with_header_delay(mut self, delay: Duration) -> Self {
+        self.header_delay = delay;
+        self
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub path: String,
+    pub headers: HashMap<String, String>,
+    pub body: String,
+}
+
+pub struct MockOllama {
+    pub base_url: String,
+    requests: Arc<Mutex<Vec<RecordedRequest>>>,
+}
+
+impl MockOllama {
+    /// Serves `routes`, keyed like `"POST /api/chat"`; anything else gets a 404.
+    pub fn start(routes: &[(&str, MockResponse)]) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind mock server");
+        let base_url = format!("http://{}", listener.local_addr().expect("mock address"));
+        let routes = Arc::new(
+            routes
+                .iter()
+                .map(|(route, response)| (route.to_string(), response.clone()))
+                .collect::<HashMap<_, _>>(),
+        );
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+        // The listener lives until the test process exits.
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let routes = routes.clone();
+                let recorded = recorded.clone();
+                thread::spawn(move || serve(stream, &routes, &recorded));
+            }
+        });
+        Self { base_url, requests }
+    }
+
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+fn read_request(stream: &TcpStream) -> Option<RecordedRequest> {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line).ok()?;
+    let mut parts = line.split_whitespace();
+    let method = parts.next()?.to_string();
+    let path = parts.next()?.to_string();
+    let mut headers = HashMap::new();
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header).ok()?;
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            headers.insert(name.trim().to_ascii_lowercase(), value.trim().to_string());
+        }
+    }
+    let length = headers
+        .get("content-length")
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(0);
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).ok()?;
+    Some(RecordedRequest {
+        method,
+        path,
+        headers,
+        body: String::from_utf8_lossy(&body).into_owned(),
+    })
+}
+
+fn serve(
+    mut stream: TcpStream,
+    routes: &HashMap<String, MockResponse>,
+    recorded: &Mutex<Vec<RecordedRequest>>,
+) {
+    let Some(request) = read_request(&stream) else {
+        return;
+    };
+    // Query strings are ignored when matching routes.
+    let path = request.path.split('?').next().unwrap_or_default();
+    let response = routes
+        .get(&format!("{} {path}", request.method))
+        .cloned()
+        .unwrap_or_else(|| MockResponse::json(404, r#"{"error":"not found"}"#));
+    recorded.lock().unwrap().push(request);
+
+    thread::sleep(response.header_delay);
+    let head = format!(
+        "HTTP/1.1 {} Mock\r\nContent-Type: {}\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\n",
+        response.status, response.content_type
+    );
+    // Write errors mean the client gave up (e.g. timed out); nothing to report.
+    if stream.write_all(head.as_bytes()).is_err() {
+        return;
+    }
+    for (delay, part) in &response.parts {
+        thread::sleep(*delay);
+        let chunk = [format!("{:x}\r\n", part.len()).as_bytes(), part, b"\r\n"].concat();
+        if stream
+            .write_all(&chunk)
+            .and_then(|_| stream.flush())
+            .is_err()
+        {
+            return;
+        }
+    }
+    let _ = stream.write_all(b"0\r\n\r\n");
+}
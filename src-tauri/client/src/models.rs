@@ -0,0 +1,50 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+// Per-model defaults from the `models` section of the app config, applied to
+// requests by `ChatRequest::apply_presets`.
+
+/// Generation defaults for one model; request values always take precedence.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ModelPreset {
+    #[serde(default)]
+    pub temperature: Option<f64>,
+    #[serde(default)]
+    pub top_p: Option<f64>,
+    #[serde(default)]
+    pub num_ctx: Option<u32>,
+    #[serde(default)]
+    pub num_thread: Option<u32>,
+    #[serde(default)]
+    pub seed: Option<i64>,
+    #[serde(default)]
+    pub stop: Option<Vec<String>>,
+    // Ollama's `think` option: true/false, or "low"/"medium"/"high" for some models.
+    #[serde(default)]
+    pub think: Option<Value>,
+    // Added as a system message when the request has none.
+    #[serde(default)]
+    pub system_prompt: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ModelsConfig {
+    #[serde(default)]
+    pub default_chat_model: Option<String>,
+    #[serde(default)]
+    pub default_vision_model: Option<String>,
+    // Keyed by model name, e.g. "llama3.2:3b" or "llama3.2" for every tag.
+    #[serde(default)]
+    pub presets: HashMap<String, ModelPreset>,
+}
+
+impl ModelsConfig {
+    /// The preset for an exact model tag, falling back to the untagged name.
+    pub fn preset_for(&self, model: &str) -> Option<&ModelPreset> {
+        self.presets.get(model).or_else(|| {
+            let (name, _) = model.split_once(':')?;
+            self.presets.get(name)
+        })
+    }
+}
@@ -0,0 +1,439 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::chat::{ChatError, ChatRequest, ChatResponse};
+use crate::http::{describe_reqwest_error, ensure_success};
+use crate::provider::{ChatProvider, StreamDecoder};
+
+const SERVICE: &str = "Ollama";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(32);
+pub(crate) const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+// Generation can go quiet while a large model loads, so this is generous.
+pub(crate) const IDLE_TIMEOUT: Duration = Duration::from_secs(300);
+
+// The frontend never calls Ollama directly; every request goes through this endpoint.
+pub struct OllamaEndpoint {
+    base_url: String,
+    auth_header: Option<String>,
+    verify_tls: bool,
+    // Whole-request timeout for quick metadata calls.
+    timeout: Duration,
+    // Longest wait for the next bytes of a generation; replies may take minutes.
+    idle_timeout: Duration,
+}
+
+impl OllamaEndpoint {
+    pub fn new(base_url: String, auth_header: Option<String>, verify_tls: bool) -> Self {
+        Self {
+            base_url,
+            auth_header,
+            verify_tls,
+            timeout: REQUEST_TIMEOUT,
+            idle_timeout: IDLE_TIMEOUT,
+        }
+    }
+
+    fn client(&self) -> Result<reqwest::Client, String> {
+        reqwest::Client::builder()
+            .timeout(self.timeout)
+            .danger_accept_invalid_certs(!self.verify_tls)
+            .build()
+            .map_err(|err| format!("HTTP client error: {err}"))
+    }
+
+    // Generations only time out when the server stops sending, not by total length.
+    fn generation_client(&self) -> Result<reqwest::Client, String> {
+        reqwest::Client::builder()
+            .connect_timeout(CONNECT_TIMEOUT)
+            .read_timeout(self.idle_timeout)
+            .danger_accept_invalid_certs(!self.verify_tls)
+            .build()
+            .map_err(|err| format!("HTTP client error: {err}"))
+    }
+
+    // Long-running transfers (model pulls) only bound the connect phase.
+    fn streaming_client(&self) -> Result<reqwest::Client, String> {
+        reqwest::Client::builder()
+            .connect_timeout(CONNECT_TIMEOUT)
+            .danger_accept_invalid_certs(!self.verify_tls)
+            .build()
+            .map_err(|err| format!("HTTP client error: {err}"))
+    }
+
+    fn request(
+        &self,
+        client: &reqwest::Client,
+        method: reqwest::Method,
+        path: &str,
+    ) -> reqwest::RequestBuilder {
+        let builder = client.request(method, format!("{}{}", self.base_url, path));
+        match &self.auth_header {
+            Some(value) => builder.header(reqwest::header::AUTHORIZATION, value),
+            None => builder,
+        }
+    }
+
+    async fn get_json<T: serde::de::DeserializeOwned>(
+        &self,
+        path: &str,
+        context: &str,
+    ) -> Result<T, String> {
+        let client = self.client()?;
+        let response = self
+            .request(&client, reqwest::Method::GET, path)
+            .send()
+            .await
+            .map_err(|err| describe_reqwest_error(&err, SERVICE))?;
+        let response = ensure_success(response, SERVICE, context).await?;
+        response
+            .json::<T>()
+            .await
+            .map_err(|err| format!("invalid {context} response: {err}"))
+    }
+
+    /// Installed models from `/api/tags`.
+    pub async fn list_models(&self) -> Result<OllamaModelList, String> {
+        self.get_json("/api/tags", "Ollama model list").await
+    }
+
+    pub async fn show(&self, model: &str) -> Result<OllamaModelInfo, String> {
+        let client = self.client()?;
+        let response = self
+            .request(&client, reqwest::Method::POST, "/api/show")
+            .json(&serde_json::json!({ "model": model }))
+            .send()
+            .await
+            .map_err(|err| {
+                let detail = describe_reqwest_error(&err, SERVICE);
+                eprintln!("Ollama model show failed: {detail}");
+                detail
+            })?;
+        let response = ensure_success(response, SERVICE, "Ollama model show").await?;
+        response.json::<OllamaModelInfo>().await.map_err(|err| {
+            let detail = format!("invalid Ollama model info: {err}");
+            eprintln!("Ollama model show failed: {detail}");
+            detail
+        })
+    }
+
+    /// Embeds each input with `/api/embed`, returning vectors in input order.
+    pub async fn embed(&self, model: &str, inputs: &[String]) -> Result<Vec<Vec<f32>>, String> {
+        // A batch on CPU, plus loading the model, easily outlasts the metadata timeout.
+        let client = self.generation_client()?;
+        let response = self
+            .request(&client, reqwest::Method::POST, "/api/embed")
+            .json(&serde_json::json!({ "model": model, "input": inputs }))
+            .send()
+            .await
+            .map_err(|err| describe_reqwest_error(&err, SERVICE))?;
+        let response = ensure_success(response, SERVICE, "Ollama embed").await?;
+        let payload = response
+            .json::<OllamaEmbedResponse>()
+            .await
+            .map_err(|err| format!("invalid Ollama embed response: {err}"))?;
+        if payload.embeddings.len() != inputs.len() {
+            return Err(format!(
+                "Ollama returned {} embeddings for {} inputs",
+                payload.embeddings.len(),
+                inputs.len()
+            ));
+        }
+        Ok(payload.embeddings)
+    }
+
+    /// Loads (or with `keep_alive: 0`, unloads) a model without generating anything.
+    pub async fn load_model(&self, model: &str, keep_alive: Value) -> Result<(), String> {
+        let client = self.generation_client()?;
+        let response = self
+            .request(&client, reqwest::Method::POST, "/api/chat")
+            .json(&serde_json::json!({
+                "model": model,
+                "messages": [],
+                "stream": false,
+                "keep_alive": keep_alive,
+            }))
+            .send()
+            .await
+            .map_err(|err| describe_reqwest_error(&err, SERVICE))?;
+        ensure_success(response, SERVICE, "Ollama model load").await?;
+        Ok(())
+    }
+
+    pub async fn version(&self) -> Result<String, String> {
+        let payload: OllamaVersion = self.get_json("/api/version", "Ollama version").await?;
+        Ok(payload.version)
+    }
+
+    /// Models currently loaded in memory (`/api/ps`).
+    pub async fn running_models(&self) -> Result<Vec<OllamaRunningModel>, String> {
+        let payload: OllamaRunningModels = self.get_json("/api/ps", "Ollama process list").await?;
+        Ok(payload.models)
+    }
+
+    pub async fn delete_model(&self, model: &str) -> Result<(), String> {
+        let client = self.client()?;
+        let response = self
+            .request(&client, reqwest::Method::DELETE, "/api/delete")
+            .json(&serde_json::json!({ "model": model }))
+            .send()
+            .await
+            .map_err(|err| {
+                let detail = describe_reqwest_error(&err, SERVICE);
+                eprintln!("Ollama model delete failed: {detail}");
+                detail
+            })?;
+        ensure_success(response, SERVICE, "Ollama model delete").await?;
+        Ok(())
+    }
+
+    /// Starts a streamed `/api/pull`; the body is NDJSON progress lines.
+    pub async fn open_pull(&self, model: &str) -> Result<reqwest::Response, String> {
+        let client = self.streaming_client()?;
+        let response = self
+            .request(&client, reqwest::Method::POST, "/api/pull")
+            .json(&serde_json::json!({ "model": model, "stream": true }))
+            .send()
+            .await
+            .map_err(|err| {
+                let detail = describe_reqwest_error(&err, SERVICE);
+                eprintln!("Ollama model pull failed: {detail}");
+                detail
+            })?;
+        ensure_success(response, SERVICE, "Ollama model pull").await
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaVersion {
+    version: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaEmbedResponse {
+    #[serde(default)]
+    embeddings: Vec<Vec<f32>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct OllamaRunningModel {
+    pub name: String,
+    #[serde(default)]
+    pub model: String,
+    #[serde(default)]
+    pub size: u64,
+    #[serde(default)]
+    pub size_vram: u64,
+    #[serde(default)]
+    pub expires_at: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaRunningModels {
+    #[serde(default)]
+    models: Vec<OllamaRunningModel>,
+}
+
+// Splits the next complete NDJSON line off the front of the buffer.
+pub fn next_ndjson_line(buffer: &mut Vec<u8>) -> Option<Vec<u8>> {
+    let pos = buffer.iter().position(|byte| *byte == b'\n')?;
+    Some(buffer.drain(..=pos).collect())
+}
+
+impl ChatProvider for OllamaEndpoint {
+    fn service(&self) -> &str {
+        SERVICE
+    }
+
+    async fn health(&self) -> Result<(), String> {
+        let client = self.client()?;
+        let request = self.request(&client, reqwest::Method::GET, "/api/tags");
+        let response = request.send().await.map_err(|err| {
+            let detail = describe_reqwest_error(&err, SERVICE);
+            eprintln!("Ollama health check failed: {detail}");
+            detail
+        })?;
+        ensure_success(response, SERVICE, "Ollama health check").await?;
+        Ok(())
+    }
+
+    async fn chat(&self, request: &ChatRequest) -> Result<ChatResponse, ChatError> {
+        let client = self.generation_client()?;
+        let mut payload = request.clone();
+        payload.stream = Some(false);
+
+        let response = self
+            .request(&client, reqwest::Method::POST, "/api/chat")
+            .json(&payload)
+            .send()
+            .await
+            .map_err(|err| {
+                let detail = describe_reqwest_error(&err, SERVICE);
+                eprintln!("Ollama chat request failed: {detail}");
+                detail
+            })?;
+        let response = ensure_success(response, SERVICE, "Ollama chat request").await?;
+
+        response.json::<ChatResponse>().await.map_err(|err| {
+            let detail = format!("invalid Ollama response: {err}");
+            eprintln!("Ollama chat request failed: {detail}");
+            detail.into()
+        })
+    }
+
+    async fn open_stream(&self, request: &ChatRequest) -> Result<reqwest::Response, ChatError> {
+        let client = self.generation_client()?;
+        let mut payload = request.clone();
+        payload.stream = Some(true);
+
+        let response = self
+            .request(&client, reqwest::Method::POST, "/api/chat")
+            .json(&payload)
+            .send()
+            .await
+            .map_err(|err| {
+                let detail = describe_reqwest_error(&err, SERVICE);
+                eprintln!("Ollama chat stream request failed: {detail}");
+                detail
+            })?;
+        Ok(ensure_success(response, SERVICE, "Ollama chat stream request").await?)
+    }
+
+    fn stream_decoder(&self) -> Box<dyn StreamDecoder> {
+        Box::new(NdjsonDecoder::default())
+    }
+}
+
+/// Ollama streams NDJSON chunks separated by newlines.
+#[derive(Default)]
+struct NdjsonDecoder {
+    buffer: Vec<u8>,
+}
+
+fn parse_ndjson_line(line: &[u8]) -> Option<Value> {
+    let line = match std::str::from_utf8(line) {
+        Ok(line) => line.trim(),
+        Err(err) => {
+            eprintln!("Ollama stream decode error: {err}");
+            return None;
+        }
+    };
+    if line.is_empty() {
+        return None;
+    }
+    match serde_json::from_str::<Value>(line) {
+        Ok(payload) => Some(payload),
+        Err(err) => {
+            eprintln!("Ollama stream parse error: {err}");
+            None
+        }
+    }
+}
+
+impl StreamDecoder for NdjsonDecoder {
+    fn push(&mut self, bytes: &[u8]) -> Vec<Value> {
+        self.buffer.extend_from_slice(bytes);
+        let mut chunks = Vec::new();
+        while let Some(line) = next_ndjson_line(&mut self.buffer) {
+            chunks.extend(parse_ndjson_line(&line));
+        }
+        chunks
+    }
+
+    fn finish(&mut self) -> Vec<Value> {
+        // The last line may arrive without a trailing newline.
+        let rest = std::mem::take(&mut self.buffer);
+        parse_ndjson_line(&rest).into_iter().collect()
+    }
+}
+
+/// Timing and token counts from the final (`done: true`) chunk.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct OllamaGenerationStats {
+    #[serde(default)]
+    pub done_reason: Option<String>,
+    #[serde(default)]
+    pub total_duration: Option<u64>,
+    #[serde(default)]
+    pub load_duration: Option<u64>,
+    #[serde(default)]
+    pub prompt_eval_count: Option<u64>,
+    #[serde(default)]
+    pub prompt_eval_duration: Option<u64>,
+    #[serde(default)]
+    pub eval_count: Option<u64>,
+    #[serde(default)]
+    pub eval_duration: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct OllamaModelDetails {
+    #[serde(default)]
+    pub format: Option<String>,
+    #[serde(default)]
+    pub family: Option<String>,
+    #[serde(default)]
+    pub families: Option<Vec<String>>,
+    #[serde(default)]
+    pub parameter_size: Option<String>,
+    #[serde(default)]
+    pub quantization_level: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OllamaModel {
+    pub name: String,
+    #[serde(default)]
+    pub modified_at: Option<String>,
+    #[serde(default)]
+    pub size: u64,
+    #[serde(default)]
+    pub digest: Option<String>,
+    #[serde(default)]
+    pub details: OllamaModelDetails,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OllamaModelList {
+    pub models: Vec<OllamaModel>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct OllamaModelInfo {
+    #[serde(default)]
+    pub modelfile: Option<String>,
+    #[serde(default)]
+    pub parameters: Option<String>,
+    #[serde(default)]
+    pub template: Option<String>,
+    #[serde(default)]
+    pub details: OllamaModelDetails,
+    // Architecture-specific keys such as `llama.context_length`.
+    #[serde(default)]
+    pub model_info: HashMap<String, Value>,
+    #[serde(default)]
+    pub capabilities: Vec<String>,
+}
+
+impl OllamaModelInfo {
+    /// The model's trained context length, e.g. from `llama.context_length`.
+    pub fn context_length(&self) -> Option<u64> {
+        self.model_info
+            .iter()
+            .find(|(key, _)| key.ends_with(".context_length"))
+            .and_then(|(_, value)| value.as_u64())
+    }
+
+    /// `num_ctx` set by the Modelfile, which Ollama uses unless the request overrides it.
+    pub fn configured_num_ctx(&self) -> Option<u64> {
+        self.parameters.as_deref()?.lines().find_map(|line| {
+            let mut parts = line.split_whitespace();
+            (parts.next()? == "num_ctx").then(|| parts.next()?.parse().ok())?
+        })
+    }
+}
+
+#[cfg(test)]
+#[path = "ollama_tests.rs"]
+mod tests;
@@ -0,0 +1,568 @@
+// Drives `ollama.rs` and the stream pump in `stream.rs` against the
+// in-process mock server in `mock_ollama.rs`.
+
+use serde_json::{json, Value};
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use super::*;
+use crate::http::describe_reqwest_error;
+use crate::metrics::GenerationMetrics;
+use crate::mock_ollama::{MockOllama, MockResponse};
+use crate::models::ModelsConfig;
+use crate::search::{OllamaSearch, SearchBackend, SearxngSearch};
+use crate::stream::{run_stream, OllamaStreamPayload, StreamEventKind, StreamSession, StreamSink};
+
+// Recorded from `ollama run llama3.2` (timestamps shortened); note the
+// multi-byte characters, which splitting must not corrupt.
+const RECORDED_STREAM: &[&str] = &[
+    "{\"model\":\"llama3.2\",\"created_at\":\"2025-01-01T00:00:00Z\",\"message\":{\"role\":\"assistant\",\"content\":\"Bonjour\"},\"done\":false}\n",
+    "{\"model\":\"llama3.2\",\"created_at\":\"2025-01-01T00:00:00Z\",\"message\":{\"role\":\"assistant\",\"content\":\", ça va\"},\"done\":false}\n",
+    "{\"model\":\"llama3.2\",\"created_at\":\"2025-01-01T00:00:00Z\",\"message\":{\"role\":\"assistant\",\"content\":\" ? 👋\"},\"done\":false}\n",
+    "{\"model\":\"llama3.2\",\"created_at\":\"2025-01-01T00:00:00Z\",\"message\":{\"role\":\"assistant\",\"content\":\"\"},\"done\":true,\"done_reason\":\"stop\",\"total_duration\":912000000,\"load_duration\":21000000,\"prompt_eval_count\":26,\"prompt_eval_duration\":130000000,\"eval_count\":9,\"eval_duration\":740000000}\n",
+];
+
+const CHAT_REPLY: &str = r#"{"model":"llama3.2","created_at":"2025-01-01T00:00:00Z","message":{"role":"assistant","content":"Hi!"},"done":true,"eval_count":3,"eval_duration":30000000}"#;
+
+fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .expect("test runtime")
+        .block_on(future)
+}
+
+fn endpoint(server: &MockOllama) -> OllamaEndpoint {
+    OllamaEndpoint::new(server.base_url.clone(), None, true)
+}
+
+fn request(value: Value) -> ChatRequest {
+    ChatRequest::from_value(value, &ModelsConfig::default()).expect("valid request")
+}
+
+fn hello() -> ChatRequest {
+    request(json!({
+        "model": "llama3.2",
+        "messages": [{ "role": "user", "content": "Hello" }],
+    }))
+}
+
+// Everything one `run_stream` call produced.
+struct StreamRun {
+    events: Vec<OllamaStreamPayload>,
+    result: Result<Option<GenerationMetrics>, String>,
+    // What the finish hook saw: the assembled answer and reasoning.
+    finished: Option<(String, String)>,
+}
+
+impl StreamRun {
+    fn kinds(&self) -> Vec<StreamEventKind> {
+        self.events.iter().map(|event| event.kind).collect()
+    }
+
+    fn last(&self) -> &OllamaStreamPayload {
+        self.events.last().expect("at least one event")
+    }
+
+    // Answer text across delta and done chunks, as the webview would see it.
+    fn text(&self) -> String {
+        self.events
+            .iter()
+            .filter_map(|event| event.chunk.as_ref()?["message"]["content"].as_str())
+            .collect()
+    }
+}
+
+// Streams `hello()` through the real pump into a collecting sink.
+fn run_with_batch(endpoint: &OllamaEndpoint, batch: Duration) -> StreamRun {
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let collected = events.clone();
+    let sink = StreamSink::new(move |payload| collected.lock().unwrap().push(payload));
+    let mut session = StreamSession::new("s1".into(), "llama3.2".into(), None, sink, batch);
+    let mut finished = None;
+    let result = block_on(run_stream(
+        endpoint,
+        &hello(),
+        &mut session,
+        &mut |session, _, _| finished = Some((session.content.clone(), session.thinking.clone())),
+    ));
+    let events = events.lock().unwrap().clone();
+    StreamRun {
+        events,
+        result,
+        finished,
+    }
+}
+
+// No batching, so each decoded chunk becomes its own event.
+fn run(endpoint: &OllamaEndpoint) -> StreamRun {
+    run_with_batch(endpoint, Duration::ZERO)
+}
+
+fn assert_recorded_reply(run: &StreamRun) {
+    use StreamEventKind::*;
+    assert_eq!(run.kinds(), [Started, Delta, Delta, Delta, Done]);
+    assert!(run.events.iter().all(|event| event.stream_id == "s1"));
+    assert_eq!(run.text(), "Bonjour, ça va ? 👋");
+    let done = run.last();
+    assert_eq!(done.stats.as_ref().unwrap().eval_count, Some(9));
+    let metrics = run.result.as_ref().expect("stream succeeds").as_ref();
+    assert_eq!(metrics.unwrap().completion_tokens, Some(9));
+    assert!(done.metrics.is_some());
+    assert_eq!(
+        run.finished,
+        Some(("Bonjour, ça va ? 👋".to_string(), String::new()))
+    );
+}
+
+// Asserts the stream ended with exactly one `error` event carrying `expected`.
+fn assert_stream_error(run: &StreamRun, expected: &str) {
+    let err = run.result.as_ref().unwrap_err();
+    assert!(err.contains(expected), "{err}");
+    let last = run.last();
+    assert_eq!(last.kind, StreamEventKind::Error);
+    assert_eq!(last.error.as_deref(), Some(err.as_str()));
+    let terminal = run
+        .kinds()
+        .into_iter()
+        .filter(|kind| !matches!(kind, StreamEventKind::Started | StreamEventKind::Delta))
+        .count();
+    assert_eq!(terminal, 1);
+    assert!(run.finished.is_none());
+}
+
+#[test]
+fn chat_returns_message_and_stats() {
+    let server = MockOllama::start(&[("POST /api/chat", MockResponse::json(200, CHAT_REPLY))]);
+    let response = block_on(endpoint(&server).chat(&hello())).expect("chat succeeds");
+    assert_eq!(response.message.content, "Hi!");
+    assert_eq!(response.stats.eval_count, Some(3));
+
+    let sent: Value = serde_json::from_str(&server.requests()[0].body).unwrap();
+    assert_eq!(sent["stream"], json!(false));
+    assert_eq!(sent["messages"][0]["content"], json!("Hello"));
+}
+
+#[test]
+fn chat_sends_auth_header() {
+    let server = MockOllama::start(&[("POST /api/chat", MockResponse::json(200, CHAT_REPLY))]);
+    let endpoint = OllamaEndpoint::new(server.base_url.clone(), Some("Bearer t0k".into()), true);
+    block_on(endpoint.chat(&hello())).expect("chat succeeds");
+    let headers = &server.requests()[0].headers;
+    assert_eq!(
+        headers.get("authorization").map(String::as_str),
+        Some("Bearer t0k")
+    );
+}
+
+#[test]
+fn chat_reports_error_body() {
+    let server = MockOllama::start(&[(
+        "POST /api/chat",
+        MockResponse::json(
+            404,
+            r#"{"error":"model \"llama3.2\" not found, try pulling it first"}"#,
+        ),
+    )]);
+    let err = block_on(endpoint(&server).chat(&hello()))
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("404"), "{err}");
+    assert!(err.contains("try pulling it first"), "{err}");
+}
+
+#[test]
+fn chat_rejects_invalid_json() {
+    let server = MockOllama::start(&[("POST /api/chat", MockResponse::json(200, "{\"message\":"))]);
+    let err = block_on(endpoint(&server).chat(&hello()))
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("invalid Ollama response"), "{err}");
+}
+
+#[test]
+fn stream_replays_recorded_chunks() {
+    let server = MockOllama::start(&[("POST /api/chat", MockResponse::ndjson(RECORDED_STREAM))]);
+    assert_recorded_reply(&run(&endpoint(&server)));
+
+    let sent: Value = serde_json::from_str(&server.requests()[0].body).unwrap();
+    assert_eq!(sent["stream"], json!(true));
+}
+
+#[test]
+fn stream_reassembles_lines_split_across_chunks() {
+    // Seven-byte pieces cut through JSON tokens and multi-byte characters.
+    let body = RECORDED_STREAM.concat().into_bytes();
+    let pieces = body.chunks(7).collect::<Vec<_>>();
+    let server = MockOllama::start(&[("POST /api/chat", MockResponse::ndjson(&pieces))]);
+    assert_recorded_reply(&run(&endpoint(&server)));
+}
+
+#[test]
+fn stream_splits_multiple_lines_in_one_chunk() {
+    let server = MockOllama::start(&[(
+        "POST /api/chat",
+        MockResponse::ndjson(&[RECORDED_STREAM.concat()]),
+    )]);
+    assert_recorded_reply(&run(&endpoint(&server)));
+}
+
+#[test]
+fn stream_keeps_final_line_without_newline() {
+    let mut lines = RECORDED_STREAM
+        .iter()
+        .map(|line| line.to_string())
+        .collect::<Vec<_>>();
+    let last = lines.pop().unwrap();
+    lines.push(last.trim_end().to_string());
+    let server = MockOllama::start(&[("POST /api/chat", MockResponse::ndjson(&lines))]);
+    assert_recorded_reply(&run(&endpoint(&server)));
+}
+
+#[test]
+fn stream_skips_blank_and_malformed_lines() {
+    let mut lines = RECORDED_STREAM
+        .iter()
+        .map(|line| line.to_string())
+        .collect::<Vec<_>>();
+    lines.insert(1, "\r\n".into());
+    lines.insert(2, "{not json}\n".into());
+    let server = MockOllama::start(&[("POST /api/chat", MockResponse::ndjson(&lines))]);
+    assert_recorded_reply(&run(&endpoint(&server)));
+}
+
+#[test]
+fn stream_passes_in_band_errors_through() {
+    let server = MockOllama::start(&[(
+        "POST /api/chat",
+        MockResponse::ndjson(&[RECORDED_STREAM[0], "{\"error\":\"out of memory\"}\n"]),
+    )]);
+    let run = run(&endpoint(&server));
+    use StreamEventKind::*;
+    assert_eq!(run.kinds(), [Started, Delta, Error]);
+    assert_stream_error(&run, "Ollama error: out of memory");
+}
+
+#[test]
+fn stream_reports_error_status() {
+    let server = MockOllama::start(&[(
+        "POST /api/chat",
+        MockResponse::json(500, r#"{"error":"llama runner process has terminated"}"#),
+    )]);
+    let run = run(&endpoint(&server));
+    // Nothing was started, so the error is the only event.
+    assert_eq!(run.kinds(), [StreamEventKind::Error]);
+    assert_stream_error(&run, "500");
+    assert_stream_error(&run, "llama runner process has terminated");
+}
+
+#[test]
+fn stream_reports_early_end() {
+    let server = MockOllama::start(&[(
+        "POST /api/chat",
+        MockResponse::ndjson(&RECORDED_STREAM[..2]),
+    )]);
+    let run = run(&endpoint(&server));
+    use StreamEventKind::*;
+    assert_eq!(run.kinds(), [Started, Delta, Delta, Error]);
+    assert_stream_error(&run, "Ollama stream ended before completion.");
+}
+
+#[test]
+fn stream_batches_deltas_before_done() {
+    let server = MockOllama::start(&[("POST /api/chat", MockResponse::ndjson(RECORDED_STREAM))]);
+    let run = run_with_batch(&endpoint(&server), Duration::from_secs(10));
+    use StreamEventKind::*;
+    // The pending batch is flushed ahead of the terminal event.
+    assert_eq!(run.kinds(), [Started, Delta, Done]);
+    assert_eq!(
+        run.events[1].chunk.as_ref().unwrap()["message"]["content"],
+        json!("Bonjour, ça va ? 👋")
+    );
+}
+
+#[test]
+fn stream_flushes_batch_when_model_stalls() {
+    let server = MockOllama::start(&[(
+        "POST /api/chat",
+        MockResponse::ndjson(RECORDED_STREAM).with_stall_before(3, Duration::from_millis(300)),
+    )]);
+    let run = run_with_batch(&endpoint(&server), Duration::from_millis(50));
+    use StreamEventKind::*;
+    assert_eq!(run.kinds(), [Started, Delta, Done]);
+}
+
+#[test]
+fn stream_outcome_collects_tool_calls_and_stats() {
+    let lines = [
+        r#"{"message":{"role":"assistant","content":"Let me check."},"done":false}"#,
+        r#"{"message":{"role":"assistant","content":"","tool_calls":[{"function":{"name":"web_search","arguments":{"query":"rust"}}}]},"done":false}"#,
+        r#"{"message":{"role":"assistant","content":""},"done":true,"done_reason":"stop","eval_count":7}"#,
+    ]
+    .map(|line| format!("{line}\n"));
+    let server = MockOllama::start(&[("POST /api/chat", MockResponse::ndjson(&lines))]);
+    let sink = StreamSink::new(|_| {});
+    let mut session =
+        StreamSession::new("s1".into(), "llama3.2".into(), None, sink, Duration::ZERO);
+    let result = block_on(run_stream(
+        &endpoint(&server),
+        &hello(),
+        &mut session,
+        &mut |_, _, _| {},
+    ));
+    let outcome = session.outcome(result);
+    assert_eq!(outcome.content, "Let me check.");
+    assert_eq!(outcome.tool_calls.len(), 1);
+    assert_eq!(outcome.tool_calls[0].function.name, "web_search");
+    assert_eq!(
+        outcome.tool_calls[0].function.arguments,
+        json!({ "query": "rust" })
+    );
+    assert_eq!(outcome.stats.and_then(|stats| stats.eval_count), Some(7));
+    assert!(outcome.error.is_none());
+}
+
+#[test]
+fn stream_moves_inline_reasoning_to_thinking() {
+    let lines = [
+        r#"{"message":{"role":"assistant","content":"<thi"},"done":false}"#,
+        r#"{"message":{"role":"assistant","content":"nk>Greet "},"done":false}"#,
+        r#"{"message":{"role":"assistant","content":"them.</think>\n\nHel"},"done":false}"#,
+        r#"{"message":{"role":"assistant","content":"lo!"},"done":true,"eval_count":4}"#,
+    ]
+    .map(|line| format!("{line}\n"));
+    let server = MockOllama::start(&[("POST /api/chat", MockResponse::ndjson(&lines))]);
+    let run = run(&endpoint(&server));
+    assert_eq!(run.text(), "Hello!");
+    let thinking = run
+        .events
+        .iter()
+        .filter_map(|event| event.chunk.as_ref()?["message"]["thinking"].as_str())
+        .collect::<String>();
+    assert_eq!(thinking, "Greet them.");
+    assert_eq!(
+        run.finished,
+        Some(("Hello!".to_string(), "Greet them.".to_string()))
+    );
+}
+
+#[test]
+fn stalled_stream_times_out() {
+    let server = MockOllama::start(&[(
+        "POST /api/chat",
+        MockResponse::ndjson(RECORDED_STREAM).with_stall_before(1, Duration::from_millis(800)),
+    )]);
+    let endpoint = OllamaEndpoint {
+        idle_timeout: Duration::from_millis(300),
+        ..endpoint(&server)
+    };
+    let run = run(&endpoint);
+    use StreamEventKind::*;
+    // The stall comes after the response opened, so it ends the stream in-band.
+    assert_eq!(run.kinds(), [Started, Delta, Error]);
+    assert_stream_error(&run, "timed out waiting for Ollama to respond");
+}
+
+#[test]
+fn long_stream_outlives_request_timeout() {
+    let server = MockOllama::start(&[(
+        "POST /api/chat",
+        MockResponse::ndjson(RECORDED_STREAM).with_chunk_delay(Duration::from_millis(80)),
+    )]);
+    // The whole reply takes longer than `timeout`, but no single gap reaches `idle_timeout`.
+    let endpoint = OllamaEndpoint {
+        timeout: Duration::from_millis(150),
+        idle_timeout: Duration::from_secs(2),
+        ..endpoint(&server)
+    };
+    assert_recorded_reply(&run(&endpoint));
+}
+
+#[test]
+fn slow_response_times_out() {
+    let server = MockOllama::start(&[(
+        "POST /api/chat",
+        MockResponse::json(200, CHAT_REPLY).with_header_delay(Duration::from_millis(500)),
+    )]);
+    let endpoint = OllamaEndpoint {
+        idle_timeout: Duration::from_millis(100),
+        ..endpoint(&server)
+    };
+    let err = block_on(endpoint.chat(&hello())).unwrap_err().to_string();
+    assert_eq!(err, "timed out waiting for Ollama to respond");
+}
+
+#[test]
+fn health_succeeds_against_running_server() {
+    let server =
+        MockOllama::start(&[("GET /api/tags", MockResponse::json(200, r#"{"models":[]}"#))]);
+    block_on(endpoint(&server).health()).expect("healthy");
+}
+
+#[test]
+fn health_reports_error_status() {
+    let server = MockOllama::start(&[(
+        "GET /api/tags",
+        MockResponse::json(503, r#"{"error":"starting"}"#),
+    )]);
+    let err = block_on(endpoint(&server).health()).unwrap_err();
+    assert!(err.contains("503"), "{err}");
+}
+
+#[test]
+fn health_reports_connection_refused() {
+    // Bind then release a port so nothing is listening on it.
+    let port = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let endpoint = OllamaEndpoint::new(format!("http://127.0.0.1:{port}"), None, true);
+    let err = block_on(endpoint.health()).unwrap_err();
+    assert_eq!(err, "connection refused by Ollama");
+}
+
+#[test]
+fn list_models_and_embed_decode() {
+    let server = MockOllama::start(&[
+        (
+            "GET /api/tags",
+            MockResponse::json(
+                200,
+                r#"{"models":[{"name":"llama3.2:latest","size":2019393189}]}"#,
+            ),
+        ),
+        (
+            "POST /api/embed",
+            MockResponse::json(
+                200,
+                r#"{"model":"nomic-embed-text","embeddings":[[0.1,0.2],[0.3,0.4]]}"#,
+            ),
+        ),
+    ]);
+    let endpoint = endpoint(&server);
+    let models = block_on(endpoint.list_models()).expect("model list");
+    assert_eq!(models.models[0].name, "llama3.2:latest");
+
+    let inputs = vec!["a".to_string(), "b".to_string()];
+    let vectors = block_on(endpoint.embed("nomic-embed-text", &inputs)).expect("embeddings");
+    assert_eq!(vectors, vec![vec![0.1, 0.2], vec![0.3, 0.4]]);
+    // A count mismatch would misalign chunks and vectors.
+    let err = block_on(endpoint.embed("nomic-embed-text", &inputs[..1])).unwrap_err();
+    assert!(err.contains("2 embeddings for 1 inputs"), "{err}");
+}
+
+#[test]
+fn slow_embed_outlives_request_timeout() {
+    let server = MockOllama::start(&[(
+        "POST /api/embed",
+        MockResponse::json(200, r#"{"embeddings":[[0.5]]}"#)
+            .with_header_delay(Duration::from_millis(300)),
+    )]);
+    let endpoint = OllamaEndpoint {
+        timeout: Duration::from_millis(100),
+        idle_timeout: Duration::from_secs(2),
+        ..endpoint(&server)
+    };
+    let vectors = block_on(endpoint.embed("nomic-embed-text", &["a".to_string()]));
+    assert_eq!(vectors.expect("embeddings"), vec![vec![0.5]]);
+}
+
+#[test]
+fn describes_timeouts_and_refused_connections() {
+    let server = MockOllama::start(&[(
+        "GET /api/tags",
+        MockResponse::json(200, r#"{"models":[]}"#).with_header_delay(Duration::from_millis(500)),
+    )]);
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_millis(100))
+        .build()
+        .unwrap();
+    let url = format!("{}/api/tags", server.base_url);
+    let err = block_on(async { client.get(url).send().await }).unwrap_err();
+    assert_eq!(
+        describe_reqwest_error(&err, "Ollama"),
+        "timed out waiting for Ollama to respond"
+    );
+
+    let port = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let err = block_on(reqwest::get(format!("http://127.0.0.1:{port}/api/tags"))).unwrap_err();
+    assert_eq!(
+        describe_reqwest_error(&err, "Ollama"),
+        "connection refused by Ollama"
+    );
+}
+
+#[test]
+fn searxng_results_are_normalized() {
+    let server = MockOllama::start(&[(
+        "GET /search",
+        MockResponse::json(
+            200,
+            r#"{"results":[
+                {"title":"Rust","url":"https://www.rust-lang.org","content":"A language   empowering everyone."},
+                {"title":"Docs","url":"https://doc.rust-lang.org"},
+                {"title":"Extra","url":"https://example.com","content":"x"}
+            ]}"#,
+        ),
+    )]);
+    let backend = SearxngSearch::new(&format!("{}/", server.base_url));
+    let results = block_on(backend.search("rust", 2)).expect("search succeeds");
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0].snippet, "A language empowering everyone.");
+    assert_eq!(results[1].content, "");
+    assert!(server.requests()[0].path.contains("format=json"));
+}
+
+#[test]
+fn ollama_web_search_sends_key_and_decodes() {
+    let server = MockOllama::start(&[(
+        "POST /api/web_search",
+        MockResponse::json(
+            200,
+            r#"{"results":[{"title":"Ollama","url":"https://ollama.com","content":"Get up and running."}]}"#,
+        ),
+    )]);
+    let backend = OllamaSearch::new(&format!("{}/api/web_search", server.base_url), "k3y".into());
+    let results = block_on(backend.search("ollama", 5)).expect("search succeeds");
+    assert_eq!(results[0].url, "https://ollama.com");
+    assert_eq!(results[0].snippet, "Get up and running.");
+
+    let sent = &server.requests()[0];
+    assert_eq!(
+        sent.headers.get("authorization").map(String::as_str),
+        Some("Bearer k3y")
+    );
+    let body: Value = serde_json::from_str(&sent.body).unwrap();
+    assert_eq!(body, json!({ "query": "ollama", "maxResults": 5 }));
+}
+
+#[test]
+fn web_search_reports_error_body() {
+    let server = MockOllama::start(&[(
+        "POST /api/web_search",
+        MockResponse::json(401, r#"{"error":"unauthorized"}"#),
+    )]);
+    let backend = OllamaSearch::new(&format!("{}/api/web_search", server.base_url), "bad".into());
+    let err = block_on(backend.search("ollama", 5)).unwrap_err();
+    assert!(err.contains("401") && err.contains("unauthorized"), "{err}");
+    // The hosted API failed, not the local server.
+    assert!(err.starts_with("non-200 from ollama.com"), "{err}");
+}
+
+#[test]
+fn searxng_errors_name_searxng() {
+    let port = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let backend = SearxngSearch::new(&format!("http://127.0.0.1:{port}"));
+    let err = block_on(backend.search("rust", 5)).unwrap_err();
+    assert_eq!(err, "connection refused by SearxNG");
+}
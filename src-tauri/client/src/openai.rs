@@ -0,0 +1,673 @@
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use crate::chat::{
+    ChatError, ChatMessage, ChatRequest, ChatResponse, ChatRole, ToolCall, ToolCallFunction,
+};
+use crate::http::{describe_reqwest_error, ensure_success};
+use crate::ollama::{OllamaGenerationStats, CONNECT_TIMEOUT, IDLE_TIMEOUT};
+use crate::provider::{ChatProvider, StreamDecoder};
+
+// OpenAI-compatible `/v1/chat/completions` backend (llama.cpp server, vLLM, ...).
+// Requests are translated from the Ollama-shaped `ChatRequest`, and responses and
+// SSE deltas are translated back so callers never see the difference.
+
+pub struct OpenAiEndpoint {
+    // Names the server in error messages.
+    service: String,
+    base_url: String,
+    api_key: Option<String>,
+    verify_tls: bool,
+    // Longest wait for the next bytes of a completion.
+    idle_timeout: Duration,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAiUsage {
+    #[serde(default)]
+    prompt_tokens: Option<u64>,
+    #[serde(default)]
+    completion_tokens: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAiFunction {
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    arguments: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAiToolCall {
+    #[serde(default)]
+    index: Option<usize>,
+    #[serde(default)]
+    id: Option<String>,
+    #[serde(default)]
+    function: Option<OpenAiFunction>,
+}
+
+// Shared by full messages and streaming deltas; every field is optional in deltas.
+#[derive(Debug, Deserialize, Default)]
+struct OpenAiMessage {
+    #[serde(default)]
+    content: Option<String>,
+    #[serde(default)]
+    reasoning_content: Option<String>,
+    #[serde(default)]
+    tool_calls: Vec<OpenAiToolCall>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAiChoice {
+    #[serde(default)]
+    message: Option<OpenAiMessage>,
+    #[serde(default)]
+    delta: Option<OpenAiMessage>,
+    #[serde(default)]
+    finish_reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAiCompletion {
+    #[serde(default)]
+    model: String,
+    #[serde(default)]
+    choices: Vec<OpenAiChoice>,
+    #[serde(default)]
+    usage: Option<OpenAiUsage>,
+}
+
+fn stats_from(finish_reason: Option<String>, usage: Option<&OpenAiUsage>) -> OllamaGenerationStats {
+    OllamaGenerationStats {
+        done_reason: finish_reason,
+        prompt_eval_count: usage.and_then(|usage| usage.prompt_tokens),
+        eval_count: usage.and_then(|usage| usage.completion_tokens),
+        ..OllamaGenerationStats::default()
+    }
+}
+
+fn image_data_url(encoded: &str) -> String {
+    // Images arrive base64-encoded from `normalize_image_paths`; sniff the common formats.
+    let mime = if encoded.starts_with("/9j/") {
+        "image/jpeg"
+    } else if encoded.starts_with("R0lGOD") {
+        "image/gif"
+    } else if encoded.starts_with("UklGR") {
+        "image/webp"
+    } else {
+        "image/png"
+    };
+    format!("data:{mime};base64,{encoded}")
+}
+
+fn role_name(role: ChatRole) -> &'static str {
+    match role {
+        ChatRole::System => "system",
+        ChatRole::User | ChatRole::Unknown => "user",
+        ChatRole::Assistant => "assistant",
+        ChatRole::Tool => "tool",
+    }
+}
+
+fn convert_messages(messages: &[ChatMessage]) -> Vec<Value> {
+    // OpenAI links tool results to calls by id; Ollama only records the tool name.
+    let mut pending_calls: Vec<(String, String)> = Vec::new();
+    let mut converted = Vec::with_capacity(messages.len());
+    for (message_index, message) in messages.iter().enumerate() {
+        let mut entry = Map::new();
+        entry.insert("role".into(), json!(role_name(message.role)));
+        if message.images.is_empty() {
+            entry.insert("content".into(), json!(message.content));
+        } else {
+            let mut parts = vec![json!({ "type": "text", "text": message.content })];
+            parts.extend(message.images.iter().map(|image| {
+                json!({ "type": "image_url", "image_url": { "url": image_data_url(image) } })
+            }));
+            entry.insert("content".into(), Value::Array(parts));
+        }
+        if !message.tool_calls.is_empty() {
+            pending_calls.clear();
+            let calls = message
+                .tool_calls
+                .iter()
+                .enumerate()
+                .map(|(index, call)| {
+                    let id = call
+                        .id
+                        .clone()
+                        .unwrap_or_else(|| format!("call_{message_index}_{index}"));
+                    pending_calls.push((call.function.name.clone(), id.clone()));
+                    let arguments = match &call.function.arguments {
+                        Value::String(raw) => raw.clone(),
+                        other => other.to_string(),
+                    };
+                    json!({
+                        "id": id,
+                        "type": "function",
+                        "function": { "name": call.function.name, "arguments": arguments },
+                    })
+                })
+                .collect::<Vec<_>>();
+            entry.insert("tool_calls".into(), Value::Array(calls));
+        }
+        if message.role == ChatRole::Tool {
+            let name = message.tool_name.clone().unwrap_or_default();
+            let position = pending_calls
+                .iter()
+                .position(|(call_name, _)| *call_name == name)
+                .unwrap_or(0);
+            if position < pending_calls.len() {
+                let (_, id) = pending_calls.remove(position);
+                entry.insert("tool_call_id".into(), json!(id));
+            }
+            if !name.is_empty() {
+                entry.insert("name".into(), json!(name));
+            }
+        }
+        converted.push(Value::Object(entry));
+    }
+    converted
+}
+
+fn convert_request(request: &ChatRequest, stream: bool) -> Value {
+    let mut body = Map::new();
+    body.insert("model".into(), json!(request.model));
+    body.insert(
+        "messages".into(),
+        Value::Array(convert_messages(&request.messages)),
+    );
+    body.insert("stream".into(), json!(stream));
+    if stream {
+        body.insert("stream_options".into(), json!({ "include_usage": true }));
+    }
+    if let Some(tools) = &request.tools {
+        body.insert("tools".into(), json!(tools));
+    }
+    match &request.format {
+        Some(Value::String(format)) if format == "json" => {
+            body.insert("response_format".into(), json!({ "type": "json_object" }));
+        }
+        Some(schema @ Value::Object(_)) => {
+            body.insert(
+                "response_format".into(),
+                json!({
+                    "type": "json_schema",
+                    "json_schema": { "name": "response", "schema": schema },
+                }),
+            );
+        }
+        _ => {}
+    }
+    if let Some(options) = &request.options {
+        if let Some(value) = options.temperature {
+            body.insert("temperature".into(), json!(value));
+        }
+        if let Some(value) = options.top_p {
+            body.insert("top_p".into(), json!(value));
+        }
+        if let Some(value) = options.seed {
+            body.insert("seed".into(), json!(value));
+        }
+        if let Some(value) = &options.stop {
+            body.insert("stop".into(), json!(value));
+        }
+        if let Some(value) = options.num_predict.filter(|value| *value > 0) {
+            body.insert("max_tokens".into(), json!(value));
+        }
+    }
+    Value::Object(body)
+}
+
+fn parse_arguments(raw: &str) -> Value {
+    serde_json::from_str(raw).unwrap_or_else(|_| Value::String(raw.to_string()))
+}
+
+fn convert_message(message: OpenAiMessage) -> ChatMessage {
+    let tool_calls = message
+        .tool_calls
+        .into_iter()
+        .filter_map(|call| {
+            let function = call.function?;
+            Some(ToolCall {
+                id: call.id,
+                function: ToolCallFunction {
+                    name: function.name.unwrap_or_default(),
+                    arguments: parse_arguments(function.arguments.as_deref().unwrap_or("{}")),
+                },
+            })
+        })
+        .collect();
+    ChatMessage {
+        tool_calls,
+        thinking: message.reasoning_content,
+        ..ChatMessage::new(ChatRole::Assistant, message.content.unwrap_or_default())
+    }
+}
+
+fn service_label(name: &str, base_url: &str) -> String {
+    let name = name.trim();
+    if !name.is_empty() {
+        return name.to_string();
+    }
+    reqwest::Url::parse(base_url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string))
+        .unwrap_or_else(|| "provider".into())
+}
+
+impl OpenAiEndpoint {
+    /// `name` is the profile's display name; the base URL's host stands in when it's blank.
+    pub fn new(name: &str, base_url: String, api_key: Option<String>, verify_tls: bool) -> Self {
+        Self {
+            service: service_label(name, &base_url),
+            base_url,
+            api_key,
+            verify_tls,
+            idle_timeout: IDLE_TIMEOUT,
+        }
+    }
+
+    fn client(&self) -> Result<reqwest::Client, String> {
+        reqwest::Client::builder()
+            .timeout(Duration::from_secs(32))
+            .danger_accept_invalid_certs(!self.verify_tls)
+            .build()
+            .map_err(|err| format!("HTTP client error: {err}"))
+    }
+
+    // Completions only time out when the server stops sending, not by total length.
+    fn completion_client(&self) -> Result<reqwest::Client, String> {
+        reqwest::Client::builder()
+            .connect_timeout(CONNECT_TIMEOUT)
+            .read_timeout(self.idle_timeout)
+            .danger_accept_invalid_certs(!self.verify_tls)
+            .build()
+            .map_err(|err| format!("HTTP client error: {err}"))
+    }
+
+    fn request(
+        &self,
+        client: &reqwest::Client,
+        method: reqwest::Method,
+        path: &str,
+    ) -> reqwest::RequestBuilder {
+        let builder = client.request(method, format!("{}{}", self.base_url, path));
+        match &self.api_key {
+            Some(key) => builder.bearer_auth(key),
+            None => builder,
+        }
+    }
+
+    async fn post_completion(
+        &self,
+        request: &ChatRequest,
+        stream: bool,
+        context: &str,
+    ) -> Result<reqwest::Response, ChatError> {
+        let client = self.completion_client()?;
+        let response = self
+            .request(&client, reqwest::Method::POST, "/chat/completions")
+            .json(&convert_request(request, stream))
+            .send()
+            .await
+            .map_err(|err| {
+                let detail = describe_reqwest_error(&err, &self.service);
+                eprintln!("{context} failed: {detail}");
+                detail
+            })?;
+        Ok(ensure_success(response, &self.service, context).await?)
+    }
+}
+
+impl ChatProvider for OpenAiEndpoint {
+    fn service(&self) -> &str {
+        &self.service
+    }
+
+    async fn health(&self) -> Result<(), String> {
+        let client = self.client()?;
+        let response = self
+            .request(&client, reqwest::Method::GET, "/models")
+            .send()
+            .await
+            .map_err(|err| {
+                let detail = describe_reqwest_error(&err, &self.service);
+                eprintln!("Provider health check failed: {detail}");
+                detail
+            })?;
+        ensure_success(response, &self.service, "Provider health check").await?;
+        Ok(())
+    }
+
+    async fn chat(&self, request: &ChatRequest) -> Result<ChatResponse, ChatError> {
+        let response = self
+            .post_completion(request, false, "Provider chat request")
+            .await?;
+        let completion = response.json::<OpenAiCompletion>().await.map_err(|err| {
+            let detail = format!("invalid provider response: {err}");
+            eprintln!("Provider chat request failed: {detail}");
+            detail
+        })?;
+        let Some(choice) = completion.choices.into_iter().next() else {
+            return Err("Provider response contained no choices.".to_string().into());
+        };
+        Ok(ChatResponse {
+            model: completion.model,
+            created_at: None,
+            message: convert_message(choice.message.unwrap_or_default()),
+            done: true,
+            stats: stats_from(choice.finish_reason, completion.usage.as_ref()),
+            context: None,
+        })
+    }
+
+    async fn open_stream(&self, request: &ChatRequest) -> Result<reqwest::Response, ChatError> {
+        self.post_completion(request, true, "Provider chat stream request")
+            .await
+    }
+
+    fn stream_decoder(&self) -> Box<dyn StreamDecoder> {
+        Box::new(OpenAiStreamDecoder::default())
+    }
+}
+
+/// Incremental parser for `text/event-stream` bodies; yields each event's data.
+#[derive(Default)]
+struct SseParser {
+    buffer: Vec<u8>,
+    data: Vec<String>,
+}
+
+impl SseParser {
+    fn push(&mut self, bytes: &[u8]) -> Vec<String> {
+        self.buffer.extend_from_slice(bytes);
+        let mut events = Vec::new();
+        while let Some(pos) = self.buffer.iter().position(|byte| *byte == b'\n') {
+            let line = self.buffer.drain(..=pos).collect::<Vec<u8>>();
+            self.handle_line(&line, &mut events);
+        }
+        events
+    }
+
+    fn finish(&mut self) -> Vec<String> {
+        let mut events = Vec::new();
+        let rest = std::mem::take(&mut self.buffer);
+        self.handle_line(&rest, &mut events);
+        // A stream may end without the blank line that terminates the last event.
+        self.handle_line(b"", &mut events);
+        events
+    }
+
+    fn handle_line(&mut self, line: &[u8], events: &mut Vec<String>) {
+        let line = String::from_utf8_lossy(line);
+        let line = line.trim_end_matches(['\n', '\r']);
+        if line.is_empty() {
+            if !self.data.is_empty() {
+                events.push(self.data.join("\n"));
+                self.data.clear();
+            }
+            return;
+        }
+        // Lines starting with ':' are comments (often keep-alives).
+        if line.starts_with(':') {
+            return;
+        }
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+        if field == "data" {
+            self.data.push(value.to_string());
+        }
+    }
+}
+
+#[derive(Default)]
+struct PartialToolCall {
+    id: Option<String>,
+    name: String,
+    arguments: String,
+}
+
+/// Turns OpenAI SSE deltas into Ollama-shaped chunks.
+#[derive(Default)]
+struct OpenAiStreamDecoder {
+    sse: SseParser,
+    model: String,
+    // Tool call arguments arrive in fragments keyed by index.
+    tool_calls: BTreeMap<usize, PartialToolCall>,
+    finish_reason: Option<String>,
+    usage: Option<OpenAiUsage>,
+    done: bool,
+}
+
+impl OpenAiStreamDecoder {
+    fn handle_event(&mut self, data: &str, chunks: &mut Vec<Value>) {
+        if self.done {
+            return;
+        }
+        if data.trim() == "[DONE]" {
+            chunks.push(self.done_chunk());
+            return;
+        }
+        let payload = match serde_json::from_str::<Value>(data) {
+            Ok(payload) => payload,
+            Err(err) => {
+                eprintln!("Provider stream parse error: {err}");
+                return;
+            }
+        };
+        if let Some(error) = payload.get("error") {
+            let message = error
+                .get("message")
+                .and_then(Value::as_str)
+                .map(str::to_string)
+                .unwrap_or_else(|| error.to_string());
+            self.done = true;
+            chunks.push(json!({ "error": message }));
+            return;
+        }
+        let completion = match serde_json::from_value::<OpenAiCompletion>(payload) {
+            Ok(completion) => completion,
+            Err(err) => {
+                eprintln!("Provider stream parse error: {err}");
+                return;
+            }
+        };
+        if !completion.model.is_empty() {
+            self.model = completion.model;
+        }
+        if completion.usage.is_some() {
+            self.usage = completion.usage;
+        }
+        for choice in completion.choices {
+            if choice.finish_reason.is_some() {
+                self.finish_reason = choice.finish_reason;
+            }
+            let Some(delta) = choice.delta else {
+                continue;
+            };
+            for call in delta.tool_calls {
+                let entry = self.tool_calls.entry(call.index.unwrap_or(0)).or_default();
+                if call.id.is_some() {
+                    entry.id = call.id;
+                }
+                if let Some(function) = call.function {
+                    entry.name.push_str(function.name.as_deref().unwrap_or(""));
+                    entry
+                        .arguments
+                        .push_str(function.arguments.as_deref().unwrap_or(""));
+                }
+            }
+            let content = delta.content.unwrap_or_default();
+            let thinking = delta.reasoning_content.unwrap_or_default();
+            if content.is_empty() && thinking.is_empty() {
+                continue;
+            }
+            let mut message = json!({ "role": "assistant", "content": content });
+            if !thinking.is_empty() {
+                message["thinking"] = json!(thinking);
+            }
+            chunks.push(json!({ "model": self.model, "message": message, "done": false }));
+        }
+    }
+
+    fn done_chunk(&mut self) -> Value {
+        self.done = true;
+        let tool_calls = std::mem::take(&mut self.tool_calls)
+            .into_values()
+            .map(|call| {
+                let arguments = if call.arguments.is_empty() {
+                    json!({})
+                } else {
+                    parse_arguments(&call.arguments)
+                };
+                json!({ "id": call.id, "function": { "name": call.name, "arguments": arguments } })
+            })
+            .collect::<Vec<_>>();
+        let mut message = json!({ "role": "assistant", "content": "" });
+        if !tool_calls.is_empty() {
+            message["tool_calls"] = Value::Array(tool_calls);
+        }
+        let stats = stats_from(self.finish_reason.take(), self.usage.as_ref());
+        let mut chunk = serde_json::to_value(stats).unwrap_or_else(|_| json!({}));
+        chunk["model"] = json!(self.model);
+        chunk["message"] = message;
+        chunk["done"] = json!(true);
+        chunk
+    }
+}
+
+impl StreamDecoder for OpenAiStreamDecoder {
+    fn push(&mut self, bytes: &[u8]) -> Vec<Value> {
+        let mut chunks = Vec::new();
+        for event in self.sse.push(bytes) {
+            self.handle_event(&event, &mut chunks);
+        }
+        chunks
+    }
+
+    fn finish(&mut self) -> Vec<Value> {
+        let mut chunks = Vec::new();
+        for event in self.sse.finish() {
+            self.handle_event(&event, &mut chunks);
+        }
+        // Some servers close the stream after the finish_reason without `[DONE]`.
+        if !self.done && self.finish_reason.is_some() {
+            chunks.push(self.done_chunk());
+        }
+        chunks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Feeds `parts` one push at a time, then finishes, collecting every chunk.
+    fn decode(parts: &[&str]) -> Vec<Value> {
+        let mut decoder = OpenAiStreamDecoder::default();
+        let mut chunks = Vec::new();
+        for part in parts {
+            chunks.extend(decoder.push(part.as_bytes()));
+        }
+        chunks.extend(decoder.finish());
+        chunks
+    }
+
+    #[test]
+    fn sse_parser_joins_lines_split_across_pushes() {
+        let mut parser = SseParser::default();
+        assert!(parser.push(b"da").is_empty());
+        assert!(parser.push(b"ta: {\"a\":").is_empty());
+        assert!(parser.push(b"1}\r\n").is_empty());
+        assert_eq!(parser.push(b"\r\n"), ["{\"a\":1}"]);
+    }
+
+    #[test]
+    fn sse_parser_skips_comments_and_joins_multiline_data() {
+        let mut parser = SseParser::default();
+        let events = parser.push(b": keep-alive\n\nevent: message\ndata: one\ndata:two\n\n");
+        assert_eq!(events, ["one\ntwo"]);
+    }
+
+    #[test]
+    fn sse_parser_flushes_unterminated_event_on_finish() {
+        let mut parser = SseParser::default();
+        assert!(parser.push(b"data: [DONE]").is_empty());
+        assert_eq!(parser.finish(), ["[DONE]"]);
+    }
+
+    #[test]
+    fn decodes_deltas_and_done() {
+        let chunks = decode(&[
+            "data: {\"model\":\"qwen\",\"choices\":[{\"delta\":{\"reasoning_content\":\"Hm.\"}}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{\"content\":\"Hi\"}}]}\n\ndata: {\"choices\":[{\"delta\":{},\"finish_reason\":\"stop\"}]}\n\n",
+            "data: {\"choices\":[],\"usage\":{\"prompt_tokens\":5,\"completion_tokens\":2}}\n\n",
+            "data: [DONE]\n\n",
+            // Anything after `[DONE]` is ignored.
+            "data: {\"choices\":[{\"delta\":{\"content\":\"late\"}}]}\n\n",
+        ]);
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks[0]["message"]["thinking"], "Hm.");
+        assert_eq!(chunks[0]["model"], "qwen");
+        assert_eq!(chunks[1]["message"]["content"], "Hi");
+        let done = &chunks[2];
+        assert_eq!(done["done"], true);
+        assert_eq!(done["done_reason"], "stop");
+        assert_eq!(done["prompt_eval_count"], 5);
+        assert_eq!(done["eval_count"], 2);
+    }
+
+    #[test]
+    fn assembles_tool_calls_from_fragments() {
+        let chunks = decode(&[
+            "data: {\"choices\":[{\"delta\":{\"tool_calls\":[{\"index\":0,\"id\":\"call_a\",\"function\":{\"name\":\"web_\",\"arguments\":\"\"}}]}}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{\"tool_calls\":[{\"index\":0,\"function\":{\"name\":\"search\",\"arguments\":\"{\\\"qu\"}}]}}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{\"tool_calls\":[{\"index\":1,\"id\":\"call_b\",\"function\":{\"name\":\"read_clipboard\"}}]}}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{\"tool_calls\":[{\"index\":0,\"function\":{\"arguments\":\"ery\\\": \\\"rust\\\"}\"}}]}}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{},\"finish_reason\":\"tool_calls\"}]}\n\n",
+            "data: [DONE]\n\n",
+        ]);
+        assert_eq!(chunks.len(), 1);
+        let calls = &chunks[0]["message"]["tool_calls"];
+        assert_eq!(
+            calls,
+            &json!([
+                { "id": "call_a", "function": { "name": "web_search", "arguments": { "query": "rust" } } },
+                { "id": "call_b", "function": { "name": "read_clipboard", "arguments": {} } },
+            ])
+        );
+        assert_eq!(chunks[0]["done_reason"], "tool_calls");
+    }
+
+    #[test]
+    fn finishes_without_done_marker_after_finish_reason() {
+        let chunks = decode(&[
+            "data: {\"choices\":[{\"delta\":{\"content\":\"Hi\"}}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{},\"finish_reason\":\"stop\"}]}",
+        ]);
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[1]["done"], true);
+    }
+
+    #[test]
+    fn reports_in_band_errors() {
+        let chunks = decode(&["data: {\"error\":{\"message\":\"model not loaded\"}}\n\n"]);
+        assert_eq!(chunks, [json!({ "error": "model not loaded" })]);
+    }
+
+    #[test]
+    fn labels_errors_with_profile_name_or_host() {
+        assert_eq!(
+            service_label(" vLLM box ", "http://10.0.0.5:8000/v1"),
+            "vLLM box"
+        );
+        assert_eq!(service_label("", "http://10.0.0.5:8000/v1"), "10.0.0.5");
+        assert_eq!(service_label("", "not a url"), "provider");
+    }
+}
@@ -0,0 +1,34 @@
+use serde_json::Value;
+use std::future::Future;
+
+use crate::chat::{ChatError, ChatRequest, ChatResponse};
+
+/// A chat backend. Streams are decoded into Ollama-shaped chunks so the
+/// lifecycle events and the webview stay protocol-agnostic.
+pub trait ChatProvider {
+    /// Names the server in error messages.
+    fn service(&self) -> &str;
+
+    fn health(&self) -> impl Future<Output = Result<(), String>> + Send;
+
+    fn chat(
+        &self,
+        request: &ChatRequest,
+    ) -> impl Future<Output = Result<ChatResponse, ChatError>> + Send;
+
+    /// Sends a streaming request; read the body with `stream_decoder`.
+    fn open_stream(
+        &self,
+        request: &ChatRequest,
+    ) -> impl Future<Output = Result<reqwest::Response, ChatError>> + Send;
+
+    fn stream_decoder(&self) -> Box<dyn StreamDecoder>;
+}
+
+pub trait StreamDecoder: Send {
+    /// Feeds raw body bytes and returns any complete chunks.
+    fn push(&mut self, bytes: &[u8]) -> Vec<Value>;
+
+    /// Flushes buffered data once the body ends.
+    fn finish(&mut self) -> Vec<Value>;
+}
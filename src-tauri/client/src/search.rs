@@ -0,0 +1,181 @@
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::time::Duration;
+
+use crate::http::{describe_reqwest_error, ensure_success};
+
+const SNIPPET_CHARS: usize = 300;
+
+/// A search hit normalized across backends so answers can cite `url`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OllamaWebSearchResult {
+    pub title: String,
+    pub url: String,
+    pub snippet: String,
+    // Page text when the backend provides it; otherwise the snippet.
+    pub content: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OllamaWebSearchResponse {
+    pub results: Vec<OllamaWebSearchResult>,
+}
+
+pub trait SearchBackend {
+    fn search(
+        &self,
+        query: &str,
+        max_results: u32,
+    ) -> impl Future<Output = Result<Vec<OllamaWebSearchResult>, String>> + Send;
+}
+
+fn build_client() -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(32))
+        .build()
+        .map_err(|err| format!("HTTP client error: {err}"))
+}
+
+fn snippet_of(text: &str) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.chars().count() <= SNIPPET_CHARS {
+        return text;
+    }
+    format!("{}…", text.chars().take(SNIPPET_CHARS).collect::<String>())
+}
+
+/// Ollama's hosted search API; needs an ollama.com API key.
+pub struct OllamaSearch {
+    api_url: String,
+    api_key: String,
+}
+
+impl OllamaSearch {
+    pub fn new(api_url: &str, api_key: String) -> Self {
+        Self {
+            api_url: api_url.to_string(),
+            api_key,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct OllamaSearchRequest<'a> {
+    query: &'a str,
+    #[serde(rename = "maxResults")]
+    max_results: u32,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaSearchHit {
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    url: String,
+    #[serde(default)]
+    content: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaSearchResponse {
+    #[serde(default)]
+    results: Vec<OllamaSearchHit>,
+}
+
+impl SearchBackend for OllamaSearch {
+    async fn search(
+        &self,
+        query: &str,
+        max_results: u32,
+    ) -> Result<Vec<OllamaWebSearchResult>, String> {
+        let response = build_client()?
+            .post(&self.api_url)
+            .header(
+                reqwest::header::AUTHORIZATION,
+                format!("Bearer {}", self.api_key),
+            )
+            .header("X-API-Key", &self.api_key)
+            .json(&OllamaSearchRequest { query, max_results })
+            .send()
+            .await
+            .map_err(|err| describe_reqwest_error(&err, "ollama.com"))?;
+        let response = ensure_success(response, "ollama.com", "Ollama web search").await?;
+        let payload = response
+            .json::<OllamaSearchResponse>()
+            .await
+            .map_err(|err| format!("invalid Ollama web search response: {err}"))?;
+        Ok(payload
+            .results
+            .into_iter()
+            .map(|hit| OllamaWebSearchResult {
+                snippet: snippet_of(&hit.content),
+                title: hit.title,
+                url: hit.url,
+                content: hit.content,
+            })
+            .collect())
+    }
+}
+
+/// A self-hosted SearxNG instance with the JSON output format enabled.
+pub struct SearxngSearch {
+    base_url: String,
+}
+
+impl SearxngSearch {
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            base_url: base_url.trim().trim_end_matches('/').to_string(),
+        }
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct SearxngHit {
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    url: String,
+    #[serde(default)]
+    content: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearxngResponse {
+    #[serde(default)]
+    results: Vec<SearxngHit>,
+}
+
+impl SearchBackend for SearxngSearch {
+    async fn search(
+        &self,
+        query: &str,
+        max_results: u32,
+    ) -> Result<Vec<OllamaWebSearchResult>, String> {
+        let response = build_client()?
+            .get(format!("{}/search", self.base_url))
+            .query(&[("q", query), ("format", "json")])
+            .send()
+            .await
+            .map_err(|err| describe_reqwest_error(&err, "SearxNG"))?;
+        let response = ensure_success(response, "SearxNG", "SearxNG search").await?;
+        let payload = response.json::<SearxngResponse>().await.map_err(|err| {
+            format!("invalid SearxNG response (is the json format enabled?): {err}")
+        })?;
+        Ok(payload
+            .results
+            .into_iter()
+            .take(max_results as usize)
+            .map(|hit| OllamaWebSearchResult {
+                snippet: snippet_of(&hit.content),
+                title: hit.title,
+                url: hit.url,
+                content: hit.content,
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,381 @@
+use futures_util::StreamExt;
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::chat::{ChatRequest, ContextReport, ToolCall};
+use crate::http::describe_reqwest_error;
+use crate::metrics::GenerationMetrics;
+use crate::ollama::OllamaGenerationStats;
+use crate::provider::{ChatProvider, StreamDecoder};
+use crate::thinking::ThinkSplitter;
+
+// Drives one chat stream from an open response to its terminal event. The app
+// decides where events go (`StreamSink`) and what happens to a finished reply,
+// so this runs the same against a real server, the mock, or a test sink.
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StreamEventKind {
+    Started,
+    Delta,
+    Done,
+    Error,
+    Cancelled,
+}
+
+// Every stream ends with exactly one `done`, `error`, or `cancelled` event.
+#[derive(Debug, Serialize, Clone)]
+pub struct OllamaStreamPayload {
+    pub stream_id: String,
+    pub kind: StreamEventKind,
+    pub chunk: Option<Value>,
+    pub error: Option<String>,
+    pub stats: Option<OllamaGenerationStats>,
+    pub metrics: Option<GenerationMetrics>,
+    pub context: Option<ContextReport>,
+}
+
+impl OllamaStreamPayload {
+    pub fn new(stream_id: &str, kind: StreamEventKind) -> Self {
+        Self {
+            stream_id: stream_id.to_string(),
+            kind,
+            chunk: None,
+            error: None,
+            stats: None,
+            metrics: None,
+            context: None,
+        }
+    }
+
+    pub fn error(stream_id: &str, detail: String) -> Self {
+        Self {
+            error: Some(detail),
+            ..Self::new(stream_id, StreamEventKind::Error)
+        }
+    }
+}
+
+/// Where stream events are delivered, e.g. a webview IPC channel.
+#[derive(Clone)]
+pub struct StreamSink(Arc<dyn Fn(OllamaStreamPayload) + Send + Sync>);
+
+impl StreamSink {
+    pub fn new(send: impl Fn(OllamaStreamPayload) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(send))
+    }
+
+    pub fn send(&self, payload: OllamaStreamPayload) {
+        (self.0)(payload);
+    }
+}
+
+/// Coalesces consecutive delta chunks so fast models don't send one IPC
+/// message per token. Text is appended; other fields take the latest value.
+pub struct DeltaBatch {
+    interval: Duration,
+    pending: Option<(Value, Instant)>,
+}
+
+fn merge_delta(batch: &mut Value, mut next: Value) {
+    let earlier = batch
+        .get_mut("message")
+        .and_then(Value::as_object_mut)
+        .map(std::mem::take);
+    let later = next.get_mut("message").and_then(Value::as_object_mut);
+    if let (Some(earlier), Some(later)) = (earlier, later) {
+        for key in ["content", "thinking"] {
+            let before = earlier.get(key).and_then(Value::as_str).unwrap_or("");
+            if before.is_empty() {
+                continue;
+            }
+            let after = later.get(key).and_then(Value::as_str).unwrap_or("");
+            later.insert(key.into(), Value::String(format!("{before}{after}")));
+        }
+    }
+    *batch = next;
+}
+
+impl DeltaBatch {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            pending: None,
+        }
+    }
+
+    /// Adds a delta; returns true when the batch should be flushed now.
+    pub fn push(&mut self, payload: Value) -> bool {
+        // Tool calls end the turn on the frontend, so never hold them back.
+        let urgent = payload
+            .pointer("/message/tool_calls")
+            .and_then(Value::as_array)
+            .is_some_and(|calls| !calls.is_empty());
+        match &mut self.pending {
+            Some((batch, _)) => merge_delta(batch, payload),
+            None => self.pending = Some((payload, Instant::now())),
+        }
+        urgent || self.deadline().is_some_and(|at| at <= Instant::now())
+    }
+
+    /// When the pending batch must be sent, if there is one.
+    pub fn deadline(&self) -> Option<Instant> {
+        self.pending
+            .as_ref()
+            .map(|(_, started)| *started + self.interval)
+    }
+
+    pub fn take(&mut self) -> Option<Value> {
+        self.pending.take().map(|(batch, _)| batch)
+    }
+}
+
+/// How a stream ended, for callers that wait on it. Cancelled streams drop
+/// the sender instead of reporting.
+#[derive(Debug, Clone)]
+pub struct StreamOutcome {
+    pub model: String,
+    pub content: String,
+    pub thinking: String,
+    pub tool_calls: Vec<ToolCall>,
+    pub stats: Option<OllamaGenerationStats>,
+    pub context: Option<ContextReport>,
+    pub metrics: Option<GenerationMetrics>,
+    pub error: Option<String>,
+}
+
+/// Per-stream state carried through the pump.
+pub struct StreamSession {
+    pub stream_id: String,
+    pub model: String,
+    pub content: String,
+    pub thinking: String,
+    pub tool_calls: Vec<ToolCall>,
+    // From the `done` chunk.
+    pub stats: Option<OllamaGenerationStats>,
+    started_at: Instant,
+    first_token_at: Option<Instant>,
+    context: Option<ContextReport>,
+    sink: StreamSink,
+    splitter: ThinkSplitter,
+    batch: DeltaBatch,
+}
+
+impl StreamSession {
+    pub fn new(
+        stream_id: String,
+        model: String,
+        context: Option<ContextReport>,
+        sink: StreamSink,
+        batch_interval: Duration,
+    ) -> Self {
+        Self {
+            stream_id,
+            model,
+            content: String::new(),
+            thinking: String::new(),
+            tool_calls: Vec::new(),
+            stats: None,
+            started_at: Instant::now(),
+            first_token_at: None,
+            context,
+            sink,
+            splitter: ThinkSplitter::default(),
+            batch: DeltaBatch::new(batch_interval),
+        }
+    }
+
+    pub fn send(&self, payload: OllamaStreamPayload) {
+        self.sink.send(payload);
+    }
+
+    pub fn outcome(self, result: Result<Option<GenerationMetrics>, String>) -> StreamOutcome {
+        let (metrics, error) = match result {
+            Ok(metrics) => (metrics, None),
+            Err(err) => (None, Some(err)),
+        };
+        StreamOutcome {
+            model: self.model,
+            content: self.content,
+            thinking: self.thinking,
+            tool_calls: self.tool_calls,
+            stats: self.stats,
+            context: self.context,
+            metrics,
+            error,
+        }
+    }
+
+    fn flush(&mut self) {
+        if let Some(batch) = self.batch.take() {
+            self.send(OllamaStreamPayload {
+                chunk: Some(batch),
+                ..OllamaStreamPayload::new(&self.stream_id, StreamEventKind::Delta)
+            });
+        }
+    }
+
+    // Flushes buffered deltas first so the terminal event is always last.
+    fn fail(&mut self, detail: String) -> Result<Option<GenerationMetrics>, String> {
+        eprintln!("Chat stream failed: {detail}");
+        self.flush();
+        self.send(OllamaStreamPayload::error(&self.stream_id, detail.clone()));
+        Err(detail)
+    }
+
+    fn accumulate(&mut self, payload: &Value) {
+        let Some(message) = payload.get("message") else {
+            return;
+        };
+        let content = message.get("content").and_then(Value::as_str);
+        let thinking = message.get("thinking").and_then(Value::as_str);
+        if self.first_token_at.is_none()
+            && (content.is_some_and(|text| !text.is_empty())
+                || thinking.is_some_and(|text| !text.is_empty()))
+        {
+            self.first_token_at = Some(Instant::now());
+        }
+        if let Some(content) = content {
+            self.content.push_str(content);
+        }
+        if let Some(thinking) = thinking {
+            self.thinking.push_str(thinking);
+        }
+        if let Some(calls) = message.get("tool_calls").and_then(Value::as_array) {
+            self.tool_calls.extend(
+                calls
+                    .iter()
+                    .filter_map(|call| serde_json::from_value(call.clone()).ok()),
+            );
+        }
+    }
+
+    fn metrics(&self, stats: &OllamaGenerationStats) -> GenerationMetrics {
+        let first_token = self
+            .first_token_at
+            .map(|at| at.duration_since(self.started_at));
+        GenerationMetrics::from_stats(&self.model, stats, first_token, self.started_at.elapsed())
+    }
+}
+
+/// Called with the finished reply before the `done` event is sent, so the
+/// app can record metrics and save history first.
+pub type FinishHook<'a> = dyn FnMut(&StreamSession, Option<&OllamaGenerationStats>, Option<&GenerationMetrics>)
+    + Send
+    + 'a;
+
+/// Opens `payload` as a stream on `provider` and pumps it into the session's
+/// sink. Failing to open is reported as an `error` event like any other.
+pub async fn run_stream<P: ChatProvider>(
+    provider: &P,
+    payload: &ChatRequest,
+    session: &mut StreamSession,
+    on_finish: &mut FinishHook<'_>,
+) -> Result<Option<GenerationMetrics>, String> {
+    // Time spent queued doesn't count towards the generation.
+    session.started_at = Instant::now();
+    match provider.open_stream(payload).await {
+        Ok(response) => {
+            let decoder = provider.stream_decoder();
+            pump_chat_stream(session, response, decoder, provider.service(), on_finish).await
+        }
+        Err(err) => {
+            eprintln!("Chat stream failed: {err}");
+            session.send(OllamaStreamPayload::error(
+                &session.stream_id,
+                err.to_string(),
+            ));
+            Err(err.to_string())
+        }
+    }
+}
+
+/// Streams an open response into the session; `service` names the server in errors.
+pub async fn pump_chat_stream(
+    session: &mut StreamSession,
+    response: reqwest::Response,
+    mut decoder: Box<dyn StreamDecoder>,
+    service: &str,
+    on_finish: &mut FinishHook<'_>,
+) -> Result<Option<GenerationMetrics>, String> {
+    session.send(OllamaStreamPayload {
+        context: session.context.clone(),
+        ..OllamaStreamPayload::new(&session.stream_id, StreamEventKind::Started)
+    });
+    let mut stream = response.bytes_stream();
+
+    loop {
+        // With deltas buffered, wake up in time to send them even if the model stalls.
+        let next = match session.batch.deadline() {
+            Some(deadline) => match tokio::time::timeout_at(deadline.into(), stream.next()).await {
+                Ok(next) => next,
+                Err(_) => {
+                    session.flush();
+                    continue;
+                }
+            },
+            None => stream.next().await,
+        };
+        let Some(chunk) = next else {
+            break;
+        };
+        match chunk {
+            Ok(bytes) => {
+                for payload in decoder.push(&bytes) {
+                    if let Some(result) = handle_stream_chunk(session, payload, service, on_finish)
+                    {
+                        return result;
+                    }
+                }
+            }
+            Err(err) => return session.fail(describe_reqwest_error(&err, service)),
+        }
+    }
+
+    for payload in decoder.finish() {
+        if let Some(result) = handle_stream_chunk(session, payload, service, on_finish) {
+            return result;
+        }
+    }
+    session.fail(format!("{service} stream ended before completion."))
+}
+
+// Sends or buffers one decoded chunk; returns the result once a terminal event was sent.
+fn handle_stream_chunk(
+    session: &mut StreamSession,
+    mut payload: Value,
+    service: &str,
+    on_finish: &mut FinishHook<'_>,
+) -> Option<Result<Option<GenerationMetrics>, String>> {
+    // Mid-stream failures are reported in-band as `{"error": "..."}`.
+    if let Some(error) = payload.get("error").and_then(Value::as_str) {
+        return Some(session.fail(format!("{service} error: {error}")));
+    }
+
+    let done = payload.get("done").and_then(Value::as_bool) == Some(true);
+    // Deltas go out already split, so every consumer sees the same answer text.
+    session.splitter.split_chunk(&mut payload, done);
+    session.accumulate(&payload);
+    if done {
+        session.flush();
+        let stats = serde_json::from_value::<OllamaGenerationStats>(payload.clone()).ok();
+        let metrics = stats.as_ref().map(|stats| session.metrics(stats));
+        session.stats = stats.clone();
+        on_finish(session, stats.as_ref(), metrics.as_ref());
+        session.send(OllamaStreamPayload {
+            chunk: Some(payload),
+            stats,
+            metrics: metrics.clone(),
+            context: session.context.clone(),
+            ..OllamaStreamPayload::new(&session.stream_id, StreamEventKind::Done)
+        });
+        return Some(Ok(metrics));
+    }
+
+    if session.batch.push(payload) {
+        session.flush();
+    }
+    None
+}
@@ -0,0 +1,251 @@
+use serde_json::{Map, Value};
+
+use crate::chat::ChatMessage;
+
+// Keeps model reasoning out of the answer. With `think` on, Ollama reports
+// reasoning in `message.thinking`, but many models still write it inline as
+// `<think>…</think>`; both end up in `thinking` so `content` is only the answer.
+
+const TAGS: [(&str, &str); 2] = [("<think>", "</think>"), ("<thinking>", "</thinking>")];
+
+/// Splits streamed content into reasoning and answer text. Tags may be cut
+/// across chunks, so a trailing partial tag is held until the next push.
+#[derive(Default)]
+pub struct ThinkSplitter {
+    // The closing tag while inside a reasoning block.
+    closing: Option<&'static str>,
+    held: String,
+    answer_started: bool,
+}
+
+fn find_tag(text: &str, tag: &str) -> Option<usize> {
+    text.as_bytes()
+        .windows(tag.len())
+        .position(|window| window.eq_ignore_ascii_case(tag.as_bytes()))
+}
+
+// Length of the longest suffix of `text` that could begin one of `tags`.
+fn partial_tag_len<'a>(text: &str, tags: impl Iterator<Item = &'a str> + Clone) -> usize {
+    let bytes = text.as_bytes();
+    (1..bytes.len().min(10) + 1)
+        .rev()
+        .find(|&len| {
+            let suffix = &bytes[bytes.len() - len..];
+            tags.clone()
+                .any(|tag| tag.len() > len && tag.as_bytes()[..len].eq_ignore_ascii_case(suffix))
+        })
+        .unwrap_or(0)
+}
+
+impl ThinkSplitter {
+    /// Returns the `(reasoning, answer)` parts of a content delta.
+    pub fn push(&mut self, text: &str) -> (String, String) {
+        let mut input = std::mem::take(&mut self.held);
+        input.push_str(text);
+        let mut rest = input.as_str();
+        let mut reasoning = String::new();
+        let mut answer = String::new();
+        loop {
+            if let Some(closing) = self.closing {
+                if let Some(at) = find_tag(rest, closing) {
+                    reasoning.push_str(&rest[..at]);
+                    rest = &rest[at + closing.len()..];
+                    self.closing = None;
+                    continue;
+                }
+                let keep = partial_tag_len(rest, std::iter::once(closing));
+                reasoning.push_str(&rest[..rest.len() - keep]);
+                self.held = rest[rest.len() - keep..].to_string();
+                break;
+            }
+            let opening = TAGS
+                .iter()
+                .filter_map(|&(open, close)| Some((find_tag(rest, open)?, open, close)))
+                .min_by_key(|(at, _, _)| *at);
+            if let Some((at, open, close)) = opening {
+                answer.push_str(&rest[..at]);
+                rest = &rest[at + open.len()..];
+                self.closing = Some(close);
+                continue;
+            }
+            let keep = partial_tag_len(rest, TAGS.iter().map(|(open, _)| *open));
+            answer.push_str(&rest[..rest.len() - keep]);
+            self.held = rest[rest.len() - keep..].to_string();
+            break;
+        }
+        (reasoning, self.answer(answer))
+    }
+
+    /// Releases held text once the content is complete.
+    pub fn finish(&mut self) -> (String, String) {
+        let held = std::mem::take(&mut self.held);
+        if self.closing.take().is_some() {
+            (held, String::new())
+        } else {
+            (String::new(), self.answer(held))
+        }
+    }
+
+    // Drops the blank lines models put between the reasoning and the answer.
+    fn answer(&mut self, text: String) -> String {
+        if self.answer_started {
+            return text;
+        }
+        let text = text.trim_start();
+        self.answer_started = !text.is_empty();
+        text.to_string()
+    }
+
+    /// Rewrites one stream chunk in place so `message.content` holds only
+    /// answer text and `message.thinking` the reasoning delta.
+    pub fn split_chunk(&mut self, chunk: &mut Value, done: bool) {
+        let Some(object) = chunk.as_object_mut() else {
+            return;
+        };
+        let content = object
+            .get("message")
+            .and_then(|message| message.get("content"))
+            .and_then(Value::as_str)
+            .unwrap_or("")
+            .to_string();
+        let (mut reasoning, mut answer) = self.push(&content);
+        if done {
+            let (rest_reasoning, rest_answer) = self.finish();
+            reasoning.push_str(&rest_reasoning);
+            answer.push_str(&rest_answer);
+        }
+        if !object.contains_key("message") {
+            if reasoning.is_empty() && answer.is_empty() {
+                return;
+            }
+            // Held text released by the final chunk still needs a message.
+            let mut message = Map::new();
+            message.insert("role".into(), Value::String("assistant".into()));
+            object.insert("message".into(), Value::Object(message));
+        }
+        let Some(message) = object.get_mut("message").and_then(Value::as_object_mut) else {
+            return;
+        };
+        if let Some(thinking) = message.get("thinking").and_then(Value::as_str) {
+            reasoning.insert_str(0, thinking);
+        }
+        message.insert("content".into(), Value::String(answer));
+        if reasoning.is_empty() {
+            message.remove("thinking");
+        } else {
+            message.insert("thinking".into(), Value::String(reasoning));
+        }
+    }
+}
+
+/// Moves inline reasoning out of a complete (non-streamed) message.
+pub fn split_message(message: &mut ChatMessage) {
+    let mut splitter = ThinkSplitter::default();
+    let (mut reasoning, mut answer) = splitter.push(&message.content);
+    let (rest_reasoning, rest_answer) = splitter.finish();
+    reasoning.push_str(&rest_reasoning);
+    answer.push_str(&rest_answer);
+    message.content = answer.trim_end().to_string();
+    let parts = [message.thinking.take().unwrap_or_default(), reasoning];
+    let thinking = parts
+        .iter()
+        .map(|part| part.trim())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    message.thinking = (!thinking.is_empty()).then_some(thinking);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Feeds `parts` as consecutive deltas and returns the joined (reasoning, answer).
+    fn split(parts: &[&str]) -> (String, String) {
+        let mut splitter = ThinkSplitter::default();
+        let (mut reasoning, mut answer) = (String::new(), String::new());
+        for part in parts {
+            let (thinking, text) = splitter.push(part);
+            reasoning.push_str(&thinking);
+            answer.push_str(&text);
+        }
+        let (thinking, text) = splitter.finish();
+        reasoning.push_str(&thinking);
+        answer.push_str(&text);
+        (reasoning, answer)
+    }
+
+    // Cuts `text` into three deltas at every pair of char boundaries.
+    fn assert_split_anywhere(text: &str, reasoning: &str, answer: &str) {
+        let cuts = (0..=text.len())
+            .filter(|&at| text.is_char_boundary(at))
+            .collect::<Vec<_>>();
+        for &first in &cuts {
+            for &second in cuts.iter().filter(|&&at| at >= first) {
+                let parts = [&text[..first], &text[first..second], &text[second..]];
+                assert_eq!(
+                    split(&parts),
+                    (reasoning.to_string(), answer.to_string()),
+                    "split as {parts:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn tags_cut_across_deltas() {
+        assert_split_anywhere(
+            "<think>Plan the reply.</think>\n\nHello!",
+            "Plan the reply.",
+            "Hello!",
+        );
+        assert_split_anywhere("<THINKING>hm</Thinking>ok", "hm", "ok");
+    }
+
+    #[test]
+    fn multibyte_chars_next_to_tags() {
+        assert_split_anywhere("é<think>ü</think>🎉", "ü", "é🎉");
+        assert_split_anywhere("<think>日本</think>語", "日本", "語");
+    }
+
+    #[test]
+    fn text_before_the_tag_stays_in_the_answer() {
+        assert_eq!(
+            split(&["Sure.", "<think>why</think>", " Done"]),
+            ("why".to_string(), "Sure. Done".to_string())
+        );
+    }
+
+    #[test]
+    fn missing_closing_tag_is_all_reasoning() {
+        assert_eq!(
+            split(&["<think>still ", "going"]),
+            ("still going".to_string(), String::new())
+        );
+        // A closing tag cut off by the end of the reply is released as reasoning.
+        assert_eq!(
+            split(&["<think>abc</thi"]),
+            ("abc</thi".to_string(), String::new())
+        );
+    }
+
+    #[test]
+    fn held_partial_tags_are_released() {
+        assert_eq!(split(&["1 <", " 2"]), (String::new(), "1 < 2".to_string()));
+        assert_eq!(split(&["a <thin"]), (String::new(), "a <thin".to_string()));
+    }
+
+    #[test]
+    fn split_message_merges_reported_and_inline_reasoning() {
+        let mut message = ChatMessage {
+            thinking: Some("Reported.".into()),
+            ..ChatMessage::new(
+                crate::chat::ChatRole::Assistant,
+                "<think>Inline.</think>\n\nAnswer.\n",
+            )
+        };
+        split_message(&mut message);
+        assert_eq!(message.content, "Answer.");
+        assert_eq!(message.thinking.as_deref(), Some("Reported.\n\nInline."));
+    }
+}
@@ -0,0 +1,494 @@
+use copilot_client::chat::{ChatError, ChatMessage, ChatRequest, ChatResponse, ChatRole, ToolCall};
+use copilot_client::stream::StreamSink;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::future::Future;
+use tauri::ipc::JavaScriptChannelId;
+use tauri::{AppHandle, Emitter, Webview};
+
+use crate::provider::Provider;
+use crate::scheduler::RequestPriority;
+use crate::{capture, clipboard, config, files, knowledge, ollama, search, sink, web};
+
+// Backend tool loop: the model may call tools, we run them here and re-query
+// until it produces a plain answer. Each round's reply streams to the caller's
+// channel under `{run_id}:{step}`; tool steps are reported as `ollama:agent` events.
+
+const MAX_TOOL_ROUNDS: usize = 6;
+
+#[derive(Debug, Serialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+enum AgentEventKind {
+    ModelResponse,
+    ToolCall,
+    ToolResult,
+    Final,
+    Error,
+}
+
+#[derive(Debug, Serialize, Clone)]
+struct AgentEvent {
+    run_id: String,
+    step: usize,
+    kind: AgentEventKind,
+    message: Option<ChatMessage>,
+    tool: Option<String>,
+    result: Option<Value>,
+    error: Option<String>,
+}
+
+impl AgentEvent {
+    fn new(run_id: &str, step: usize, kind: AgentEventKind) -> Self {
+        Self {
+            run_id: run_id.to_string(),
+            step,
+            kind,
+            message: None,
+            tool: None,
+            result: None,
+            error: None,
+        }
+    }
+}
+
+fn tool_schema(name: &str, description: &str, parameters: Value) -> Value {
+    json!({
+        "type": "function",
+        "function": {
+            "name": name,
+            "description": description,
+            "parameters": parameters,
+        },
+    })
+}
+
+/// Tool definitions for every tool the current config allows.
+pub fn tool_definitions(config: &config::OverlayConfig) -> Vec<Value> {
+    let tools = [
+        tool_schema(
+            "capture_screen_image",
+            "Capture a screenshot of the current screen and attach it to the conversation.",
+            json!({ "type": "object", "properties": {} }),
+        ),
+        tool_schema(
+            "clipboard_context",
+            "Read the user's clipboard text to provide extra context when it helps.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "max_chars": {
+                        "type": "number",
+                        "description": "Maximum number of characters to read from the clipboard.",
+                    },
+                },
+                "required": [],
+            }),
+        ),
+        tool_schema(
+            "read_file",
+            "Read a small UTF-8 text file from the user's machine.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "path": { "type": "string", "description": "Absolute path of the file." },
+                },
+                "required": ["path"],
+            }),
+        ),
+        tool_schema(
+            "fetch_url",
+            "Download a web page and return its readable text with numbered link references.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "url": { "type": "string", "description": "The http(s) URL to fetch." },
+                },
+                "required": ["url"],
+            }),
+        ),
+        tool_schema(
+            "search_knowledge",
+            "Search the user's indexed documents and return the most relevant passages with their file paths.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "query": { "type": "string", "description": "What to look for in the documents." },
+                    "max_results": {
+                        "type": "number",
+                        "description": "Maximum number of passages to return.",
+                    },
+                },
+                "required": ["query"],
+            }),
+        ),
+        tool_schema(
+            "web_search",
+            "Search the web for fresh information and return concise, relevant results.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "query": { "type": "string", "description": "The search query to run." },
+                    "max_results": {
+                        "type": "number",
+                        "description": "Maximum number of results to return.",
+                    },
+                },
+                "required": ["query"],
+            }),
+        ),
+    ];
+    tools
+        .into_iter()
+        .filter(|tool| {
+            tool["function"]["name"]
+                .as_str()
+                .is_some_and(|name| config::tool_enabled(config, name))
+        })
+        .collect()
+}
+
+// Models sometimes send arguments as a JSON-encoded string instead of an object.
+fn tool_arguments(call: &ToolCall) -> Value {
+    match &call.function.arguments {
+        Value::String(raw) => serde_json::from_str(raw).unwrap_or(Value::Null),
+        other => other.clone(),
+    }
+}
+
+fn to_value<T: Serialize>(value: T) -> Result<Value, String> {
+    serde_json::to_value(value).map_err(|err| format!("Failed to encode tool result: {err}"))
+}
+
+/// Runs one tool call. The second value is an image path to show the model, if any.
+async fn execute_tool(app: &AppHandle, call: &ToolCall) -> Result<(Value, Option<String>), String> {
+    let name = call.function.name.as_str();
+    let args = tool_arguments(call);
+    match name {
+        "capture_screen_image" => {
+            let capture = capture::capture_screen_image(app.clone()).await?;
+            let file_path = capture.file_path.clone();
+            let mut result = to_value(capture)?;
+            // The preview is for the UI; the model gets the full image as an attachment.
+            if let Some(obj) = result.as_object_mut() {
+                obj.remove("preview_base64");
+            }
+            Ok((result, Some(file_path)))
+        }
+        "clipboard_context" => {
+            let max_chars = args
+                .get("max_chars")
+                .and_then(Value::as_f64)
+                .map(|value| value.max(0.0) as usize);
+            let text = clipboard::read_clipboard_text(app.clone(), max_chars)?;
+            Ok((to_value(text)?, None))
+        }
+        "read_file" => {
+            let Some(path) = args.get("path").and_then(Value::as_str) else {
+                return Err("Missing 'path' argument.".into());
+            };
+            Ok((to_value(files::read_file(path.to_string())?)?, None))
+        }
+        "web_search" => {
+            let Some(query) = args.get("query").and_then(Value::as_str) else {
+                return Err("Missing 'query' argument.".into());
+            };
+            let max_results = args
+                .get("max_results")
+                .and_then(Value::as_f64)
+                .map(|value| value.max(1.0) as u32);
+            let results =
+                search::ollama_web_search(app.clone(), query.to_string(), max_results).await?;
+            Ok((to_value(results)?, None))
+        }
+        "fetch_url" => {
+            let Some(url) = args.get("url").and_then(Value::as_str) else {
+                return Err("Missing 'url' argument.".into());
+            };
+            Ok((to_value(web::fetch_readable(url).await?)?, None))
+        }
+        "search_knowledge" => {
+            let Some(query) = args.get("query").and_then(Value::as_str) else {
+                return Err("Missing 'query' argument.".into());
+            };
+            let max_results = args
+                .get("max_results")
+                .and_then(Value::as_f64)
+                .map(|value| value.max(1.0) as u32);
+            Ok((
+                to_value(knowledge::search(app, query, max_results).await?)?,
+                None,
+            ))
+        }
+        _ => Err(format!("Unknown tool '{name}'.")),
+    }
+}
+
+// Calls `send` for each model round and `execute` for each enabled tool call
+// until the model answers without tools. Steps are passed to `report`.
+async fn tool_loop<S, SFut, E, EFut>(
+    config: &config::OverlayConfig,
+    mut request: ChatRequest,
+    run_id: &str,
+    mut send: S,
+    mut execute: E,
+    mut report: impl FnMut(AgentEvent),
+) -> Result<ChatResponse, ChatError>
+where
+    S: FnMut(ChatRequest, usize) -> SFut,
+    SFut: Future<Output = Result<ChatResponse, ChatError>>,
+    E: FnMut(ToolCall) -> EFut,
+    EFut: Future<Output = Result<(Value, Option<String>), String>>,
+{
+    if request.tools.is_none() {
+        request.tools = Some(tool_definitions(config));
+    }
+
+    for step in 0..MAX_TOOL_ROUNDS {
+        let response = send(request.clone(), step).await?;
+        let tool_calls = response.message.tool_calls.clone();
+        if tool_calls.is_empty() {
+            report(AgentEvent {
+                message: Some(response.message.clone()),
+                ..AgentEvent::new(run_id, step, AgentEventKind::Final)
+            });
+            return Ok(response);
+        }
+
+        report(AgentEvent {
+            message: Some(response.message.clone()),
+            ..AgentEvent::new(run_id, step, AgentEventKind::ModelResponse)
+        });
+        request.messages.push(response.message);
+
+        let mut attachments = Vec::new();
+        for call in tool_calls {
+            let name = call.function.name.clone();
+            report(AgentEvent {
+                tool: Some(name.clone()),
+                result: Some(tool_arguments(&call)),
+                ..AgentEvent::new(run_id, step, AgentEventKind::ToolCall)
+            });
+            // Checked here rather than trusted to `tools`: the model may call anything.
+            let outcome = if config::tool_enabled(config, &name) {
+                execute(call).await
+            } else {
+                Err(format!("Tool '{name}' is disabled in settings."))
+            };
+            // Tool failures go back to the model so it can recover or explain.
+            let (result, error) = match outcome {
+                Ok((result, attachment)) => {
+                    attachments.extend(attachment);
+                    (json!({ "ok": true, "result": result }), None)
+                }
+                Err(err) => {
+                    eprintln!("Agent tool '{name}' failed: {err}");
+                    (json!({ "ok": false, "error": err }), Some(err))
+                }
+            };
+            report(AgentEvent {
+                tool: Some(name.clone()),
+                result: Some(result.clone()),
+                error,
+                ..AgentEvent::new(run_id, step, AgentEventKind::ToolResult)
+            });
+            let mut message = ChatMessage::new(ChatRole::Tool, result.to_string());
+            message.tool_name = Some(name);
+            request.messages.push(message);
+        }
+
+        // Tool messages can't carry images, so captures are attached as a user turn.
+        if !attachments.is_empty() {
+            let mut message = ChatMessage::new(
+                ChatRole::User,
+                "Screenshot captured by capture_screen_image.",
+            );
+            message.images = attachments;
+            request.messages.push(message);
+        }
+    }
+
+    Err(format!("Tool loop stopped after {MAX_TOOL_ROUNDS} rounds without a final answer.").into())
+}
+
+// Streams one round to `sink` and assembles the reply the loop continues from.
+async fn stream_round(
+    app: &AppHandle,
+    payload: ChatRequest,
+    stream_id: String,
+    sink: StreamSink,
+) -> Result<ChatResponse, ChatError> {
+    let provider = Provider::resolve(app)?;
+    let outcome = ollama::start_stream(
+        app,
+        provider,
+        payload,
+        stream_id,
+        None,
+        RequestPriority::Interactive,
+        sink,
+    )
+    .await?
+    .await
+    .map_err(|_| "The agent run was cancelled.".to_string())?;
+    if let Some(err) = outcome.error {
+        return Err(err.into());
+    }
+    let message = ChatMessage {
+        thinking: (!outcome.thinking.is_empty()).then_some(outcome.thinking),
+        tool_calls: outcome.tool_calls,
+        ..ChatMessage::new(ChatRole::Assistant, outcome.content)
+    };
+    Ok(ChatResponse {
+        model: outcome.model,
+        created_at: None,
+        message,
+        done: true,
+        stats: outcome.stats.unwrap_or_default(),
+        context: outcome.context,
+    })
+}
+
+pub async fn run_agent(
+    app: &AppHandle,
+    request: ChatRequest,
+    run_id: &str,
+    sink: StreamSink,
+) -> Result<ChatResponse, ChatError> {
+    tool_loop(
+        &config::load_overlay_config(app),
+        request,
+        run_id,
+        move |payload, step| stream_round(app, payload, format!("{run_id}:{step}"), sink.clone()),
+        |call| async move { execute_tool(app, &call).await },
+        |event| {
+            let _ = app.emit("ollama:agent", event);
+        },
+    )
+    .await
+}
+
+#[tauri::command]
+pub async fn ollama_agent_chat(
+    app: AppHandle,
+    webview: Webview,
+    request: Value,
+    run_id: String,
+    on_event: Option<JavaScriptChannelId>,
+) -> Result<ChatResponse, ChatError> {
+    let sink = sink::resolve(&app, on_event.map(|id| id.channel_on(webview)));
+    let request = ChatRequest::from_value(request, &config::load_overlay_config(&app).models)?;
+    let result = run_agent(&app, request, &run_id, sink).await;
+    if let Err(err) = &result {
+        let _ = app.emit(
+            "ollama:agent",
+            AgentEvent {
+                error: Some(err.to_string()),
+                ..AgentEvent::new(&run_id, 0, AgentEventKind::Error)
+            },
+        );
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use copilot_client::chat::ToolCallFunction;
+    use copilot_client::models::ModelsConfig;
+
+    fn request() -> ChatRequest {
+        ChatRequest::from_value(
+            json!({ "model": "llama3.2", "messages": [{ "role": "user", "content": "Hi" }] }),
+            &ModelsConfig::default(),
+        )
+        .unwrap()
+    }
+
+    fn reply(content: &str, tool: Option<&str>) -> ChatResponse {
+        let mut message = ChatMessage::new(ChatRole::Assistant, content);
+        message.tool_calls = tool
+            .map(|name| ToolCall {
+                id: None,
+                function: ToolCallFunction {
+                    name: name.into(),
+                    arguments: json!({ "path": "/etc/hosts" }),
+                },
+            })
+            .into_iter()
+            .collect();
+        ChatResponse {
+            model: "llama3.2".into(),
+            created_at: None,
+            message,
+            done: true,
+            stats: Default::default(),
+            context: None,
+        }
+    }
+
+    // Runs the loop against scripted replies; returns the result, every request
+    // sent, how many tools actually ran, and the reported events.
+    fn run(
+        config: &config::OverlayConfig,
+        mut replies: impl FnMut(usize) -> ChatResponse,
+    ) -> (
+        Result<ChatResponse, ChatError>,
+        Vec<ChatRequest>,
+        usize,
+        Vec<AgentEvent>,
+    ) {
+        let mut sent = Vec::new();
+        let mut executed = 0;
+        let mut events = Vec::new();
+        let result = tauri::async_runtime::block_on(tool_loop(
+            config,
+            request(),
+            "run",
+            |request, step| {
+                sent.push(request);
+                let next = replies(step);
+                async move { Ok(next) }
+            },
+            |_| {
+                executed += 1;
+                async { Ok((json!("done"), None)) }
+            },
+            |event| events.push(event),
+        ));
+        (result, sent, executed, events)
+    }
+
+    #[test]
+    fn stops_after_max_tool_rounds() {
+        let mut config = config::OverlayConfig::default();
+        config.tools.tool_toggles.insert("read_file".into(), true);
+        let (result, sent, executed, _) = run(&config, |_| reply("", Some("read_file")));
+        let err = result.unwrap_err().to_string();
+        assert!(err.contains(&format!("{MAX_TOOL_ROUNDS} rounds")), "{err}");
+        assert_eq!(sent.len(), MAX_TOOL_ROUNDS);
+        assert_eq!(executed, MAX_TOOL_ROUNDS);
+    }
+
+    #[test]
+    fn refuses_disabled_tools_and_tells_the_model() {
+        let config = config::OverlayConfig::default();
+        assert!(!config::tool_enabled(&config, "read_file"));
+        let (result, sent, executed, events) = run(&config, |step| match step {
+            0 => reply("", Some("read_file")),
+            _ => reply("I can't read files.", None),
+        });
+        assert_eq!(result.unwrap().message.content, "I can't read files.");
+        assert_eq!(executed, 0);
+
+        let tool_message = sent[1].messages.last().unwrap();
+        assert_eq!(tool_message.role, ChatRole::Tool);
+        let body = serde_json::from_str::<Value>(&tool_message.content).unwrap();
+        assert_eq!(body["ok"], false);
+        assert_eq!(body["error"], "Tool 'read_file' is disabled in settings.");
+        let refused = events
+            .iter()
+            .find(|event| matches!(event.kind, AgentEventKind::ToolResult))
+            .unwrap();
+        assert!(refused.error.is_some());
+    }
+}
@@ -27,6 +27,7 @@ pub struct CaptureResult {
 
 const MAX_IMAGE_DIM: u32 = 1280;
 const MAX_CAPTURE_FILES: usize = 10;
+const JPEG_QUALITY: u8 = 85;
 
 fn encode_preview(png: &EncodedPng) -> String {
     base64::engine::general_purpose::STANDARD.encode(&png.bytes)
@@ -285,6 +286,121 @@ fn encode_png(image: &DynamicImage) -> Result<Vec<u8>, String> {
     Ok(buffer.into_inner())
 }
 
+fn encode_jpeg(image: &DynamicImage) -> Result<Vec<u8>, String> {
+    let rgb = image.to_rgb8();
+    let mut buffer = Vec::new();
+    image::codecs::jpeg::JpegEncoder::new_with_quality(&mut buffer, JPEG_QUALITY)
+        .encode(&rgb, rgb.width(), rgb.height(), image::ColorType::Rgb8)
+        .map_err(|err| format!("JPEG encode failed: {err}"))?;
+    Ok(buffer)
+}
+
+// Reads the EXIF Orientation tag (1-8) from a TIFF structure, as embedded in
+// JPEG APP1 and WebP EXIF chunks or as a whole TIFF file.
+fn tiff_orientation(tiff: &[u8]) -> Option<u16> {
+    let little_endian = match tiff.get(..2)? {
+        b"II" => true,
+        b"MM" => false,
+        _ => return None,
+    };
+    let u16_at = |at: usize| {
+        let bytes = [*tiff.get(at)?, *tiff.get(at + 1)?];
+        Some(if little_endian {
+            u16::from_le_bytes(bytes)
+        } else {
+            u16::from_be_bytes(bytes)
+        })
+    };
+    let u32_at = |at: usize| {
+        let bytes: [u8; 4] = tiff.get(at..at + 4)?.try_into().ok()?;
+        Some(if little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        })
+    };
+    let ifd = u32_at(4)? as usize;
+    let entries = u16_at(ifd)? as usize;
+    (0..entries)
+        .map(|index| ifd + 2 + index * 12)
+        .find(|entry| u16_at(*entry) == Some(0x0112))
+        .and_then(|entry| u16_at(entry + 8))
+}
+
+fn exif_orientation(bytes: &[u8], format: ImageFormat) -> Option<u16> {
+    match format {
+        ImageFormat::Jpeg => {
+            // Walk the marker segments up to the start of scan looking for APP1 "Exif".
+            let mut at = 2;
+            while bytes.get(at) == Some(&0xFF) {
+                let marker = *bytes.get(at + 1)?;
+                if marker == 0xDA {
+                    return None;
+                }
+                let length = u16::from_be_bytes([*bytes.get(at + 2)?, *bytes.get(at + 3)?]);
+                let segment = bytes.get(at + 4..at + 2 + length as usize)?;
+                if marker == 0xE1 {
+                    if let Some(tiff) = segment.strip_prefix(b"Exif\0\0") {
+                        return tiff_orientation(tiff);
+                    }
+                }
+                at += 2 + length as usize;
+            }
+            None
+        }
+        ImageFormat::WebP => {
+            // RIFF chunks after the 12-byte header; odd sizes are padded.
+            let mut at = 12;
+            while let Some(header) = bytes.get(at..at + 8) {
+                let size = u32::from_le_bytes(header[4..].try_into().ok()?) as usize;
+                if &header[..4] == b"EXIF" {
+                    let chunk = bytes.get(at + 8..at + 8 + size)?;
+                    return tiff_orientation(chunk.strip_prefix(b"Exif\0\0").unwrap_or(chunk));
+                }
+                at += 8 + size + size % 2;
+            }
+            None
+        }
+        ImageFormat::Tiff => tiff_orientation(bytes),
+        _ => None,
+    }
+}
+
+// Turns the stored pixels upright, since re-encoding drops the tag that said how.
+fn apply_orientation(image: DynamicImage, orientation: u16) -> DynamicImage {
+    match orientation {
+        2 => image.fliph(),
+        3 => image.rotate180(),
+        4 => image.flipv(),
+        5 => image.rotate90().fliph(),
+        6 => image.rotate90(),
+        7 => image.rotate270().fliph(),
+        8 => image.rotate270(),
+        _ => image,
+    }
+}
+
+/// Re-encodes an image attachment for the model using the capture size policy.
+/// JPEGs stay JPEG and everything else (WebP, BMP, TIFF, GIF...) becomes PNG.
+/// Re-encoding also drops EXIF and other metadata, which the encoders never write,
+/// so the EXIF orientation is applied to the pixels first.
+pub fn prepare_model_image(bytes: &[u8]) -> Result<Vec<u8>, String> {
+    let format = image::guess_format(bytes)
+        .map_err(|_| "Unsupported or unrecognized image format.".to_string())?;
+    let image = image::load_from_memory_with_format(bytes, format)
+        .map_err(|err| format!("Image decode failed: {err}"))?;
+    let image = match exif_orientation(bytes, format) {
+        Some(orientation) => apply_orientation(image, orientation),
+        None => image,
+    };
+    let (resized, _) = downscale_image(image);
+    if format == ImageFormat::Jpeg {
+        encode_jpeg(&resized)
+    } else {
+        encode_png(&resized)
+    }
+}
+
 fn save_capture(app: &AppHandle, png: &EncodedPng) -> Result<PathBuf, String> {
     let dir = app
         .path()
@@ -377,3 +493,71 @@ fn window_title(hwnd: windows::Win32::Foundation::HWND) -> Option<String> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A 16x8 JPEG, red on the left half and blue on the right, carrying an EXIF
+    // Orientation tag (big-endian, like most cameras write it).
+    fn tagged_jpeg(orientation: u16) -> Vec<u8> {
+        let pixels = image::RgbImage::from_fn(16, 8, |x, _| {
+            if x < 8 {
+                image::Rgb([255, 0, 0])
+            } else {
+                image::Rgb([0, 0, 255])
+            }
+        });
+        let jpeg = encode_jpeg(&DynamicImage::ImageRgb8(pixels)).unwrap();
+
+        let mut tiff = b"MM\0\x2a\0\0\0\x08\0\x01".to_vec();
+        tiff.extend_from_slice(&[0x01, 0x12, 0, 3, 0, 0, 0, 1]);
+        tiff.extend_from_slice(&orientation.to_be_bytes());
+        tiff.extend_from_slice(&[0, 0, 0, 0, 0, 0]);
+        let mut segment = b"Exif\0\0".to_vec();
+        segment.extend_from_slice(&tiff);
+
+        let mut tagged = jpeg[..2].to_vec();
+        tagged.extend_from_slice(&[0xFF, 0xE1]);
+        tagged.extend_from_slice(&(segment.len() as u16 + 2).to_be_bytes());
+        tagged.extend_from_slice(&segment);
+        tagged.extend_from_slice(&jpeg[2..]);
+        tagged
+    }
+
+    #[test]
+    fn reads_exif_orientation() {
+        for orientation in 1..=8 {
+            let jpeg = tagged_jpeg(orientation);
+            assert_eq!(
+                exif_orientation(&jpeg, ImageFormat::Jpeg),
+                Some(orientation)
+            );
+        }
+        let untagged = encode_jpeg(&DynamicImage::new_rgb8(16, 8)).unwrap();
+        assert_eq!(exif_orientation(&untagged, ImageFormat::Jpeg), None);
+    }
+
+    #[test]
+    fn prepared_images_are_upright() {
+        // Orientation 6 means the stored pixels must be rotated 90° clockwise.
+        let prepared = prepare_model_image(&tagged_jpeg(6)).unwrap();
+        let image = image::load_from_memory(&prepared).unwrap().to_rgb8();
+        assert_eq!(image.dimensions(), (8, 16));
+        // Red started on the left, so it ends up on top.
+        assert!(
+            image.get_pixel(4, 3)[0] > 200,
+            "{:?}",
+            image.get_pixel(4, 3)
+        );
+        assert!(
+            image.get_pixel(4, 12)[2] > 200,
+            "{:?}",
+            image.get_pixel(4, 12)
+        );
+
+        let untouched = prepare_model_image(&tagged_jpeg(1)).unwrap();
+        let image = image::load_from_memory(&untouched).unwrap();
+        assert_eq!((image.width(), image.height()), (16, 8));
+    }
+}
@@ -0,0 +1,181 @@
+use copilot_client::chat::{ChatError, ChatRequest};
+use copilot_client::metrics::GenerationMetrics;
+use copilot_client::stream::{OllamaStreamPayload, StreamOutcome};
+use futures_util::stream::{FuturesUnordered, StreamExt};
+use serde::Serialize;
+use serde_json::Value;
+use std::time::Instant;
+use tauri::ipc::JavaScriptChannelId;
+use tauri::{AppHandle, Webview};
+
+use crate::config;
+use crate::ollama;
+use crate::provider::Provider;
+use crate::scheduler::RequestPriority;
+use crate::sink;
+
+// Side-by-side comparison: one request fanned out to several models. Each model
+// streams to the caller's channel under `{stream_id}:{index}`, and the command
+// returns a summary once every model has finished.
+
+const MAX_COMPARE_MODELS: usize = 8;
+
+#[derive(Debug, Serialize, Clone)]
+pub struct CompareResult {
+    pub model: String,
+    pub stream_id: String,
+    pub content: String,
+    pub thinking: Option<String>,
+    // Wall time from dispatch to the end of the stream, including time queued.
+    pub latency_ms: f64,
+    pub metrics: Option<GenerationMetrics>,
+    pub error: Option<String>,
+    pub cancelled: bool,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct CompareSummary {
+    pub stream_id: String,
+    // In the order the models were requested.
+    pub results: Vec<CompareResult>,
+    pub fastest_model: Option<String>,
+    pub total_ms: f64,
+}
+
+fn fastest(results: &[CompareResult]) -> Option<String> {
+    results
+        .iter()
+        .filter(|result| result.error.is_none() && !result.cancelled)
+        .filter_map(|result| {
+            let rate = result.metrics.as_ref()?.tokens_per_second?;
+            Some((rate, &result.model))
+        })
+        .max_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(_, model)| model.clone())
+}
+
+/// Runs `request` against each of `models`, `concurrency` at a time
+/// (default 1, i.e. sequentially). Generations still pass through the
+/// scheduler, so `scheduler.max_concurrent` caps real parallelism.
+#[tauri::command]
+pub async fn ollama_chat_compare(
+    app: AppHandle,
+    webview: Webview,
+    request: Value,
+    models: Vec<String>,
+    stream_id: String,
+    concurrency: Option<u32>,
+    on_event: Option<JavaScriptChannelId>,
+) -> Result<CompareSummary, ChatError> {
+    let sink = sink::resolve(&app, on_event.map(|id| id.channel_on(webview)));
+    let mut unique = Vec::new();
+    for model in models.iter().map(|model| model.trim()) {
+        if !model.is_empty() && !unique.iter().any(|seen| seen == model) {
+            unique.push(model.to_string());
+        }
+    }
+    if unique.is_empty() {
+        return Err("Pick at least one model to compare.".to_string().into());
+    }
+    if unique.len() > MAX_COMPARE_MODELS {
+        return Err(format!("Compare at most {MAX_COMPARE_MODELS} models at once.").into());
+    }
+    let models_config = config::load_overlay_config(&app).models;
+    let mut requests = Vec::with_capacity(unique.len());
+    for model in &unique {
+        // Parsed per model so each gets its own preset.
+        let mut value = request.clone();
+        if let Some(object) = value.as_object_mut() {
+            object.insert("model".into(), Value::String(model.clone()));
+        }
+        requests.push(ChatRequest::from_value(value, &models_config)?);
+    }
+    // Fail fast on a bad provider profile rather than once per model.
+    Provider::resolve(&app)?;
+    let limit = concurrency.unwrap_or(1).clamp(1, unique.len() as u32) as usize;
+
+    let started_at = Instant::now();
+    let mut results = unique
+        .iter()
+        .enumerate()
+        .map(|(index, model)| CompareResult {
+            model: model.clone(),
+            stream_id: format!("{stream_id}:{index}"),
+            content: String::new(),
+            thinking: None,
+            latency_ms: 0.0,
+            metrics: None,
+            error: None,
+            cancelled: false,
+        })
+        .collect::<Vec<_>>();
+    let mut pending = requests.into_iter().enumerate();
+    let mut running = FuturesUnordered::new();
+    loop {
+        while running.len() < limit {
+            let Some((index, payload)) = pending.next() else {
+                break;
+            };
+            let dispatched = Instant::now();
+            let started = match Provider::resolve(&app) {
+                Ok(provider) => {
+                    ollama::start_stream(
+                        &app,
+                        provider,
+                        payload,
+                        results[index].stream_id.clone(),
+                        None,
+                        RequestPriority::Interactive,
+                        sink.clone(),
+                    )
+                    .await
+                }
+                Err(err) => Err(err.into()),
+            };
+            match started {
+                Ok(outcome) => running.push(async move { (index, dispatched, outcome.await) }),
+                // One model failing to start (e.g. no vision support) doesn't stop the rest,
+                // but its pane still needs a terminal event.
+                Err(err) => {
+                    let message = err.to_string();
+                    sink.send(OllamaStreamPayload::error(
+                        &results[index].stream_id,
+                        message.clone(),
+                    ));
+                    results[index].error = Some(message);
+                }
+            }
+        }
+        let Some((index, dispatched, outcome)) = running.next().await else {
+            break;
+        };
+        let result = &mut results[index];
+        result.latency_ms = dispatched.elapsed().as_secs_f64() * 1000.0;
+        match outcome {
+            Ok(StreamOutcome {
+                model,
+                content,
+                thinking,
+                metrics,
+                error,
+                ..
+            }) => {
+                // Vision routing may have answered with a different model.
+                result.model = model;
+                result.content = content;
+                result.thinking = (!thinking.is_empty()).then_some(thinking);
+                result.metrics = metrics;
+                result.error = error;
+            }
+            // The sender is dropped when the sub-stream is cancelled.
+            Err(_) => result.cancelled = true,
+        }
+    }
+
+    Ok(CompareSummary {
+        stream_id,
+        fastest_model: fastest(&results),
+        results,
+        total_ms: started_at.elapsed().as_secs_f64() * 1000.0,
+    })
+}
@@ -1,7 +1,8 @@
 use crate::{
     overlay::{snap_overlay_to_corner, OverlayCorner, OverlayState},
-    shortcuts,
+    server, shortcuts,
 };
+use copilot_client::models::ModelsConfig;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
@@ -32,6 +33,113 @@ pub struct ToolConfig {
     pub agents_sdk_enabled: bool,
     #[serde(default)]
     pub tool_toggles: HashMap<String, bool>,
+    #[serde(default)]
+    pub web_search_backend: WebSearchBackend,
+    // Base URL of a SearxNG instance with `format: json` enabled.
+    #[serde(default)]
+    pub searxng_url: String,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum WebSearchBackend {
+    #[default]
+    Ollama,
+    Searxng,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EndpointConfig {
+    #[serde(default = "default_ollama_base_url")]
+    pub base_url: String,
+    // The header value itself lives in the keyring; see `secrets.rs`.
+    #[serde(default)]
+    pub use_auth_header: bool,
+    #[serde(default = "default_verify_tls")]
+    pub verify_tls: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LocalServerConfig {
+    // When set, the app launches and supervises `ollama serve` itself.
+    #[serde(default)]
+    pub managed: bool,
+    // Passed as OLLAMA_HOST; keep `endpoint.base_url` pointing at the same address.
+    #[serde(default = "default_ollama_host")]
+    pub host: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WarmupConfig {
+    // Preload the model when the overlay is shown.
+    #[serde(default = "default_warmup_enabled")]
+    pub enabled: bool,
+    // Ollama duration such as "30m", or "-1" to keep the model loaded.
+    #[serde(default = "default_keep_alive")]
+    pub keep_alive: String,
+    // Model to preload; defaults to the last model used, then `models.default_chat_model`.
+    #[serde(default)]
+    pub model: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SchedulerConfig {
+    // Generations allowed to run at once; extra requests wait in the queue.
+    #[serde(default = "default_max_concurrent")]
+    pub max_concurrent: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StreamingConfig {
+    // Callers without a channel always get global `ollama:chunk` events; this
+    // only silences the warning for frontends that rely on them on purpose.
+    #[serde(default)]
+    pub legacy_events: bool,
+    // Token deltas are coalesced for up to this long; 0 sends each one immediately.
+    #[serde(default = "default_batch_ms")]
+    pub batch_ms: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KnowledgeConfig {
+    // Folders indexed for `search_knowledge`; added by `index_folder`.
+    #[serde(default)]
+    pub folders: Vec<String>,
+    #[serde(default = "default_embedding_model")]
+    pub embedding_model: String,
+    // Target chunk size in characters; consecutive chunks share `chunk_overlap`.
+    #[serde(default = "default_chunk_chars")]
+    pub chunk_chars: u32,
+    #[serde(default = "default_chunk_overlap")]
+    pub chunk_overlap: u32,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProviderKind {
+    Ollama,
+    OpenaiCompatible,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderProfile {
+    pub id: String,
+    #[serde(default)]
+    pub name: String,
+    pub kind: ProviderKind,
+    // OpenAI-compatible servers expect the `/v1` prefix here.
+    pub base_url: String,
+    #[serde(default = "default_verify_tls")]
+    pub verify_tls: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderConfig {
+    // `ollama` is the built-in profile backed by the `endpoint` section.
+    #[serde(default = "default_active_profile")]
+    pub active_profile: String,
+    #[serde(default)]
+    pub profiles: Vec<ProviderProfile>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -62,6 +170,52 @@ fn default_agents_sdk_enabled() -> bool {
     false
 }
 
+fn default_ollama_base_url() -> String {
+    "http://localhost:11434".into()
+}
+
+fn default_verify_tls() -> bool {
+    true
+}
+
+fn default_warmup_enabled() -> bool {
+    true
+}
+
+fn default_keep_alive() -> String {
+    "30m".into()
+}
+
+fn default_max_concurrent() -> u32 {
+    1
+}
+
+fn default_batch_ms() -> u32 {
+    24
+}
+
+fn default_embedding_model() -> String {
+    "nomic-embed-text".into()
+}
+
+fn default_chunk_chars() -> u32 {
+    1200
+}
+
+fn default_chunk_overlap() -> u32 {
+    200
+}
+
+fn default_ollama_host() -> String {
+    "127.0.0.1:11434".into()
+}
+
+pub const BUILTIN_OLLAMA_PROFILE: &str = "ollama";
+
+fn default_active_profile() -> String {
+    BUILTIN_OLLAMA_PROFILE.into()
+}
+
 impl Default for KeybindConfig {
     fn default() -> Self {
         Self {
@@ -96,10 +250,87 @@ impl Default for ToolConfig {
             web_search_enabled: default_web_search_enabled(),
             agents_sdk_enabled: default_agents_sdk_enabled(),
             tool_toggles: HashMap::new(),
+            web_search_backend: WebSearchBackend::default(),
+            searxng_url: String::new(),
+        }
+    }
+}
+
+impl Default for EndpointConfig {
+    fn default() -> Self {
+        Self {
+            base_url: default_ollama_base_url(),
+            use_auth_header: false,
+            verify_tls: default_verify_tls(),
+        }
+    }
+}
+
+impl Default for LocalServerConfig {
+    fn default() -> Self {
+        Self {
+            managed: false,
+            host: default_ollama_host(),
+        }
+    }
+}
+
+impl Default for WarmupConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_warmup_enabled(),
+            keep_alive: default_keep_alive(),
+            model: None,
+        }
+    }
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrent: default_max_concurrent(),
+        }
+    }
+}
+
+impl Default for StreamingConfig {
+    fn default() -> Self {
+        Self {
+            legacy_events: false,
+            batch_ms: default_batch_ms(),
         }
     }
 }
 
+impl Default for KnowledgeConfig {
+    fn default() -> Self {
+        Self {
+            folders: Vec::new(),
+            embedding_model: default_embedding_model(),
+            chunk_chars: default_chunk_chars(),
+            chunk_overlap: default_chunk_overlap(),
+        }
+    }
+}
+
+impl Default for ProviderConfig {
+    fn default() -> Self {
+        Self {
+            active_profile: default_active_profile(),
+            profiles: Vec::new(),
+        }
+    }
+}
+
+impl ProviderConfig {
+    /// The selected custom profile, or `None` for the built-in Ollama endpoint.
+    pub fn active(&self) -> Option<&ProviderProfile> {
+        self.profiles
+            .iter()
+            .find(|profile| profile.id == self.active_profile)
+    }
+}
+
 impl Default for AppearanceConfig {
     fn default() -> Self {
         Self {
@@ -118,6 +349,22 @@ pub struct OverlayConfig {
     pub appearance: AppearanceConfig,
     #[serde(default)]
     pub tools: ToolConfig,
+    #[serde(default)]
+    pub endpoint: EndpointConfig,
+    #[serde(default)]
+    pub providers: ProviderConfig,
+    #[serde(default)]
+    pub local_server: LocalServerConfig,
+    #[serde(default)]
+    pub scheduler: SchedulerConfig,
+    #[serde(default)]
+    pub warmup: WarmupConfig,
+    #[serde(default)]
+    pub models: ModelsConfig,
+    #[serde(default)]
+    pub knowledge: KnowledgeConfig,
+    #[serde(default)]
+    pub streaming: StreamingConfig,
 }
 // Adding a new config setting:
 // - Add the field + serde default here (or in the nested config struct).
@@ -132,6 +379,14 @@ impl Default for OverlayConfig {
             keybinds: KeybindConfig::default(),
             appearance: AppearanceConfig::default(),
             tools: ToolConfig::default(),
+            endpoint: EndpointConfig::default(),
+            providers: ProviderConfig::default(),
+            local_server: LocalServerConfig::default(),
+            scheduler: SchedulerConfig::default(),
+            warmup: WarmupConfig::default(),
+            models: ModelsConfig::default(),
+            knowledge: KnowledgeConfig::default(),
+            streaming: StreamingConfig::default(),
         }
     }
 }
@@ -144,6 +399,22 @@ pub fn capture_tool_enabled(app: &AppHandle) -> bool {
     config.tools.capture_screen_text_enabled
 }
 
+/// Whether a model-callable tool may run, mirroring `isToolEnabled` in `overlay/tools/registry.ts`.
+pub fn tool_enabled(config: &OverlayConfig, name: &str) -> bool {
+    if let Some(enabled) = config.tools.tool_toggles.get(name) {
+        return *enabled;
+    }
+    match name {
+        "capture_screen_image" => config.tools.capture_screen_text_enabled,
+        "web_search" => config.tools.web_search_enabled,
+        // Model-driven file reads stay opt-in.
+        "read_file" | "fetch_url" => false,
+        // Nothing to search until a folder has been indexed.
+        "search_knowledge" => !config.knowledge.folders.is_empty(),
+        _ => true,
+    }
+}
+
 pub fn set_capture_tool_enabled_value(app: &AppHandle, enabled: bool) {
     let mut config = load_overlay_config(app);
     config.tools.capture_screen_text_enabled = enabled;
@@ -234,6 +505,12 @@ pub fn set_overlay_config(
         snap_overlay_to_corner(window, normalized.corner);
     }
     shortcuts::register_overlay_shortcut(&app, &normalized);
+    server::apply_config(&app, normalized.local_server.managed);
     let _ = app.emit("config:updated", normalized.clone());
     Ok(())
 }
+
+/// Re-broadcast the current config, e.g. after a secret that affects it changes.
+pub fn emit_config_updated(app: &AppHandle) {
+    let _ = app.emit("config:updated", load_overlay_config(app));
+}
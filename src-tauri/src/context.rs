@@ -0,0 +1,232 @@
+use copilot_client::chat::{ChatMessage, ChatRequest, ChatRole, ContextReport};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+
+use crate::provider::Provider;
+
+// Keeps requests inside the model's context window. Without this Ollama
+// silently drops the earliest tokens, which usually means the system prompt.
+
+// Rough average for English text with BPE tokenizers.
+const CHARS_PER_TOKEN: usize = 4;
+// Role markers and template wrapping around each message.
+const MESSAGE_OVERHEAD_TOKENS: u64 = 4;
+// Vision encoders use a few hundred tokens per image; overestimate slightly.
+const IMAGE_TOKENS: u64 = 768;
+// Ollama's default `num_ctx` when neither the request nor the Modelfile sets one.
+const DEFAULT_NUM_CTX: u64 = 4096;
+// Room left for the reply when the request doesn't set `num_predict`.
+const DEFAULT_RESPONSE_RESERVE: u64 = 1024;
+
+#[derive(Debug, Clone, Copy, Default)]
+struct ModelLimits {
+    trained: Option<u64>,
+    // `num_ctx` from the Modelfile.
+    configured: Option<u64>,
+}
+
+/// `/api/show` lookups keyed by model name.
+#[derive(Default)]
+pub struct ContextCache {
+    models: Mutex<HashMap<String, ModelLimits>>,
+}
+
+fn estimate_text_tokens(text: &str) -> u64 {
+    text.chars().count().div_ceil(CHARS_PER_TOKEN) as u64
+}
+
+pub fn estimate_message_tokens(message: &ChatMessage) -> u64 {
+    let mut tokens = MESSAGE_OVERHEAD_TOKENS + estimate_text_tokens(&message.content);
+    if let Some(thinking) = &message.thinking {
+        tokens += estimate_text_tokens(thinking);
+    }
+    for call in &message.tool_calls {
+        tokens += estimate_text_tokens(&call.function.name);
+        tokens += estimate_text_tokens(&call.function.arguments.to_string());
+    }
+    tokens + IMAGE_TOKENS * message.images.len() as u64
+}
+
+async fn model_limits(app: &AppHandle, provider: &Provider, model: &str) -> ModelLimits {
+    let cache = app.state::<ContextCache>();
+    if let Some(limits) = cache
+        .models
+        .lock()
+        .ok()
+        .and_then(|models| models.get(model).copied())
+    {
+        return limits;
+    }
+    let Provider::Ollama(endpoint) = provider else {
+        return ModelLimits::default();
+    };
+    match endpoint.show(model).await {
+        Ok(info) => {
+            let limits = ModelLimits {
+                trained: info.context_length(),
+                configured: info.configured_num_ctx(),
+            };
+            if let Ok(mut models) = cache.models.lock() {
+                models.insert(model.to_string(), limits);
+            }
+            limits
+        }
+        // Not cached, so a model pulled later is picked up on the next request.
+        Err(err) => {
+            eprintln!("Context length lookup failed for {model}: {err}");
+            ModelLimits::default()
+        }
+    }
+}
+
+/// Removes the oldest non-system messages until the estimate fits `budget`,
+/// recording what was dropped in `report`. The last message is always kept.
+fn drop_oldest(messages: &mut Vec<ChatMessage>, budget: u64, report: &mut ContextReport) {
+    let mut costs = messages
+        .iter()
+        .map(estimate_message_tokens)
+        .collect::<Vec<_>>();
+    let mut total = costs.iter().sum::<u64>();
+    let mut index = 0;
+    while total > budget && index + 1 < messages.len() {
+        if messages[index].role == ChatRole::System {
+            index += 1;
+            continue;
+        }
+        total -= costs[index];
+        report.dropped_tokens += costs[index];
+        report.dropped_messages += 1;
+        messages.remove(index);
+        costs.remove(index);
+        // Tool results are meaningless without the call that produced them.
+        while index + 1 < messages.len() && messages[index].role == ChatRole::Tool {
+            total -= costs[index];
+            report.dropped_tokens += costs[index];
+            report.dropped_messages += 1;
+            messages.remove(index);
+            costs.remove(index);
+        }
+    }
+    report.estimated_tokens = total;
+}
+
+/// Drops the oldest non-system turns until the request fits the model's window.
+/// The latest message and all system messages are always kept. Returns `None`
+/// for providers whose context length isn't known.
+pub async fn fit_to_context(
+    app: &AppHandle,
+    provider: &Provider,
+    request: &mut ChatRequest,
+) -> Option<ContextReport> {
+    let options = request.options.as_ref();
+    let requested_ctx = options.and_then(|options| options.num_ctx).map(u64::from);
+    let limits = if matches!(provider, Provider::Ollama(_)) {
+        model_limits(app, provider, &request.model).await
+    } else if requested_ctx.is_none() {
+        return None;
+    } else {
+        ModelLimits::default()
+    };
+    let mut context_length = requested_ctx
+        .or(limits.configured)
+        .unwrap_or(DEFAULT_NUM_CTX);
+    if let Some(trained) = limits.trained {
+        context_length = context_length.min(trained);
+    }
+
+    let reserve = options
+        .and_then(|options| options.num_predict)
+        .filter(|limit| *limit > 0)
+        .map(|limit| limit as u64)
+        .unwrap_or(DEFAULT_RESPONSE_RESERVE)
+        .min(context_length / 4);
+    let budget = context_length - reserve;
+
+    let mut report = ContextReport {
+        context_length,
+        ..ContextReport::default()
+    };
+    drop_oldest(&mut request.messages, budget, &mut report);
+
+    if report.dropped_messages > 0 {
+        eprintln!(
+            "Trimmed {} messages (~{} tokens) to fit {} context for {}",
+            report.dropped_messages, report.dropped_tokens, context_length, request.model
+        );
+    }
+    Some(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(role: ChatRole, chars: usize) -> ChatMessage {
+        ChatMessage::new(role, "x".repeat(chars))
+    }
+
+    fn trim(messages: &mut Vec<ChatMessage>, budget: u64) -> ContextReport {
+        let mut report = ContextReport::default();
+        drop_oldest(messages, budget, &mut report);
+        report
+    }
+
+    #[test]
+    fn keeps_system_messages_and_the_latest_turn() {
+        // 100 chars is 25 tokens plus 4 of overhead.
+        let mut messages = vec![
+            message(ChatRole::System, 100),
+            message(ChatRole::User, 100),
+            message(ChatRole::Assistant, 100),
+            message(ChatRole::User, 100),
+        ];
+        let report = trim(&mut messages, 60);
+        let roles = messages
+            .iter()
+            .map(|message| message.role)
+            .collect::<Vec<_>>();
+        assert_eq!(roles, [ChatRole::System, ChatRole::User]);
+        assert_eq!(report.dropped_messages, 2);
+        assert_eq!(report.dropped_tokens, 58);
+        assert_eq!(report.estimated_tokens, 58);
+    }
+
+    #[test]
+    fn drops_tool_results_with_their_call() {
+        let mut messages = vec![
+            message(ChatRole::Assistant, 40),
+            message(ChatRole::Tool, 40),
+            message(ChatRole::Tool, 40),
+            message(ChatRole::User, 40),
+        ];
+        trim(&mut messages, 20);
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].role, ChatRole::User);
+    }
+
+    #[test]
+    fn counts_each_image_against_the_budget() {
+        let mut with_image = message(ChatRole::User, 0);
+        with_image.images = vec!["a".into(), "b".into()];
+        assert_eq!(
+            estimate_message_tokens(&with_image),
+            MESSAGE_OVERHEAD_TOKENS + 2 * IMAGE_TOKENS
+        );
+        let mut messages = vec![with_image, message(ChatRole::User, 0)];
+        let report = trim(&mut messages, IMAGE_TOKENS);
+        assert_eq!(messages.len(), 1);
+        assert_eq!(
+            report.dropped_tokens,
+            MESSAGE_OVERHEAD_TOKENS + 2 * IMAGE_TOKENS
+        );
+    }
+
+    #[test]
+    fn keeps_the_last_message_even_when_it_alone_is_over_budget() {
+        let mut messages = vec![message(ChatRole::User, 40), message(ChatRole::User, 4_000)];
+        let report = trim(&mut messages, 100);
+        assert_eq!(messages.len(), 1);
+        assert_eq!(report.estimated_tokens, 1_004);
+    }
+}
@@ -1,6 +1,6 @@
 use serde::Serialize;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 const MAX_FILE_BYTES: u64 = 1_000_000;
 
@@ -11,12 +11,11 @@ pub struct ReadFileResponse {
     pub content: String,
 }
 
-#[tauri::command]
-pub fn read_file(path: String) -> Result<ReadFileResponse, String> {
-    // Keep reads bounded for safety; this tool is meant for small text files.
-    let path = PathBuf::from(path);
+/// Reads a bounded UTF-8 text file; shared by `read_file` and the knowledge index.
+pub fn read_text_file(path: &Path) -> Result<(String, u64), String> {
+    // Keep reads bounded for safety; this is meant for small text files.
     let metadata =
-        fs::metadata(&path).map_err(|err| format!("Unable to read file metadata: {err}"))?;
+        fs::metadata(path).map_err(|err| format!("Unable to read file metadata: {err}"))?;
     if !metadata.is_file() {
         return Err("Path is not a file.".into());
     }
@@ -28,13 +27,19 @@ pub fn read_file(path: String) -> Result<ReadFileResponse, String> {
         ));
     }
 
-    let bytes = fs::read(&path).map_err(|err| format!("Unable to read file: {err}"))?;
+    let bytes = fs::read(path).map_err(|err| format!("Unable to read file: {err}"))?;
     let content =
         String::from_utf8(bytes).map_err(|_| "File is not valid UTF-8 text.".to_string())?;
+    Ok((content, metadata.len()))
+}
 
+#[tauri::command]
+pub fn read_file(path: String) -> Result<ReadFileResponse, String> {
+    let path = PathBuf::from(path);
+    let (content, bytes) = read_text_file(&path)?;
     Ok(ReadFileResponse {
         path: path.to_string_lossy().into_owned(),
-        bytes: metadata.len(),
+        bytes,
         content,
     })
 }
@@ -0,0 +1,89 @@
+use copilot_client::ollama::OllamaRunningModel;
+use copilot_client::provider::ChatProvider;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::overlay::OverlayState;
+use crate::provider::Provider;
+
+// Background monitor: polls the active provider and emits `ollama:health`
+// whenever it goes up or down. Polling pauses while the overlay is hidden.
+
+const UP_INTERVAL: Duration = Duration::from_secs(15);
+const RETRY_MIN: Duration = Duration::from_secs(2);
+const RETRY_MAX: Duration = Duration::from_secs(60);
+const HIDDEN_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OllamaHealthPayload {
+    pub ok: bool,
+    pub error: Option<String>,
+    pub version: Option<String>,
+    pub loaded_models: Vec<OllamaRunningModel>,
+}
+
+async fn probe(app: &AppHandle) -> OllamaHealthPayload {
+    let provider = match Provider::resolve(app) {
+        Ok(provider) => provider,
+        Err(err) => return OllamaHealthPayload::down(err),
+    };
+    if let Err(err) = provider.health().await {
+        return OllamaHealthPayload::down(err);
+    }
+    let mut payload = OllamaHealthPayload {
+        ok: true,
+        error: None,
+        version: None,
+        loaded_models: Vec::new(),
+    };
+    // Version and loaded models are Ollama-only extras; failures don't mark it down.
+    if let Provider::Ollama(endpoint) = &provider {
+        payload.version = endpoint.version().await.ok();
+        payload.loaded_models = endpoint.running_models().await.unwrap_or_default();
+    }
+    payload
+}
+
+impl OllamaHealthPayload {
+    fn down(error: String) -> Self {
+        Self {
+            ok: false,
+            error: Some(error),
+            version: None,
+            loaded_models: Vec::new(),
+        }
+    }
+}
+
+pub async fn run_health_monitor(app: AppHandle) {
+    let mut last_ok: Option<bool> = None;
+    let mut retry_delay = RETRY_MIN;
+    loop {
+        // Always probe once at startup, even though the overlay starts hidden.
+        if last_ok.is_some() && !app.state::<OverlayState>().is_visible() {
+            tokio::time::sleep(HIDDEN_CHECK_INTERVAL).await;
+            continue;
+        }
+
+        let payload = probe(&app).await;
+        let ok = payload.ok;
+        if last_ok != Some(ok) {
+            if let Some(error) = &payload.error {
+                eprintln!("Ollama health check failed: {error}");
+            }
+            let _ = app.emit("ollama:health", payload);
+            last_ok = Some(ok);
+        }
+
+        let delay = if ok {
+            retry_delay = RETRY_MIN;
+            UP_INTERVAL
+        } else {
+            let delay = retry_delay;
+            retry_delay = (retry_delay * 2).min(RETRY_MAX);
+            delay
+        };
+        tokio::time::sleep(delay).await;
+    }
+}
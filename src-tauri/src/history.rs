@@ -0,0 +1,345 @@
+use copilot_client::chat::ChatRole;
+use copilot_client::ollama::OllamaGenerationStats;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State};
+
+// One JSON file per conversation under `app_data_dir/history`, matching how
+// config.json is stored. Writes go through `HistoryStore` so they don't interleave.
+
+const HISTORY_DIR: &str = "history";
+const DEFAULT_TITLE: &str = "New conversation";
+const SNIPPET_CHARS: usize = 160;
+const DEFAULT_SEARCH_LIMIT: usize = 50;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryMessage {
+    #[serde(default)]
+    pub id: String,
+    pub role: ChatRole,
+    #[serde(default)]
+    pub content: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thinking: Option<String>,
+    // Capture/attachment file paths, never base64 payloads.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub images: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stats: Option<OllamaGenerationStats>,
+    #[serde(default)]
+    pub created_at: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Conversation {
+    pub id: String,
+    pub title: String,
+    pub created_at: u64,
+    pub updated_at: u64,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub messages: Vec<HistoryMessage>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConversationSummary {
+    pub id: String,
+    pub title: String,
+    pub created_at: u64,
+    pub updated_at: u64,
+    pub model: Option<String>,
+    pub message_count: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HistorySearchHit {
+    pub conversation_id: String,
+    pub title: String,
+    pub message_id: Option<String>,
+    pub snippet: String,
+}
+
+#[derive(Default)]
+pub struct HistoryStore {
+    lock: Mutex<()>,
+}
+
+pub fn now_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn new_id() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let sequence = COUNTER.fetch_add(1, Ordering::Relaxed);
+    format!("{:x}-{:x}", now_millis(), sequence)
+}
+
+fn history_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|err| format!("Failed to locate data dir: {err}"))?
+        .join(HISTORY_DIR);
+    fs::create_dir_all(&dir).map_err(|err| format!("Failed to create history dir: {err}"))?;
+    Ok(dir)
+}
+
+fn conversation_path(app: &AppHandle, id: &str) -> Result<PathBuf, String> {
+    // Ids become file names, so reject anything that could escape the directory.
+    let valid = !id.is_empty()
+        && id
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || ch == '-' || ch == '_');
+    if !valid {
+        return Err(format!("Invalid conversation id: '{id}'"));
+    }
+    Ok(history_dir(app)?.join(format!("{id}.json")))
+}
+
+fn read_conversation(app: &AppHandle, id: &str) -> Result<Conversation, String> {
+    let path = conversation_path(app, id)?;
+    let contents = fs::read_to_string(&path)
+        .map_err(|err| format!("Unable to read conversation '{id}': {err}"))?;
+    serde_json::from_str(&contents).map_err(|err| format!("Corrupt conversation '{id}': {err}"))
+}
+
+fn write_conversation(app: &AppHandle, conversation: &Conversation) -> Result<(), String> {
+    let path = conversation_path(app, &conversation.id)?;
+    let payload = serde_json::to_string_pretty(conversation)
+        .map_err(|err| format!("Failed to encode conversation: {err}"))?;
+    // Write then rename so a crash never leaves a half-written file.
+    let temp = path.with_extension("json.tmp");
+    fs::write(&temp, payload).map_err(|err| format!("Failed to write conversation: {err}"))?;
+    fs::rename(&temp, &path).map_err(|err| format!("Failed to save conversation: {err}"))
+}
+
+fn read_all(app: &AppHandle) -> Result<Vec<Conversation>, String> {
+    let dir = history_dir(app)?;
+    let entries = fs::read_dir(&dir).map_err(|err| format!("Failed to list history: {err}"))?;
+    let conversations = entries
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                return None;
+            }
+            let contents = fs::read_to_string(&path).ok()?;
+            match serde_json::from_str::<Conversation>(&contents) {
+                Ok(conversation) => Some(conversation),
+                Err(err) => {
+                    eprintln!("Skipping corrupt conversation {}: {err}", path.display());
+                    None
+                }
+            }
+        })
+        .collect();
+    Ok(conversations)
+}
+
+fn summarize(conversation: &Conversation) -> ConversationSummary {
+    ConversationSummary {
+        id: conversation.id.clone(),
+        title: conversation.title.clone(),
+        created_at: conversation.created_at,
+        updated_at: conversation.updated_at,
+        model: conversation.model.clone(),
+        message_count: conversation.messages.len(),
+    }
+}
+
+fn snippet(text: &str, query: &str) -> String {
+    let lower = text.to_lowercase();
+    let start_byte = lower.find(query).unwrap_or(0);
+    // `lower` can differ in byte length from `text`, so work in chars.
+    let start_char = lower[..start_byte].chars().count();
+    let skip = start_char.saturating_sub(SNIPPET_CHARS / 4);
+    let clipped = text
+        .chars()
+        .skip(skip)
+        .take(SNIPPET_CHARS)
+        .collect::<String>();
+    if skip > 0 {
+        format!("…{clipped}")
+    } else {
+        clipped
+    }
+}
+
+fn title_from(content: &str) -> Option<String> {
+    let line = content
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())?;
+    Some(line.chars().take(60).collect())
+}
+
+/// Appends a message, creating the conversation file on first use.
+pub fn append_message(
+    app: &AppHandle,
+    conversation_id: &str,
+    mut message: HistoryMessage,
+) -> Result<HistoryMessage, String> {
+    let store = app.state::<HistoryStore>();
+    let _guard = store.lock.lock().unwrap_or_else(|err| err.into_inner());
+    let now = now_millis();
+    let mut conversation = match read_conversation(app, conversation_id) {
+        Ok(conversation) => conversation,
+        Err(_) if !conversation_path(app, conversation_id)?.exists() => Conversation {
+            id: conversation_id.to_string(),
+            title: DEFAULT_TITLE.to_string(),
+            created_at: now,
+            updated_at: now,
+            model: None,
+            messages: Vec::new(),
+        },
+        Err(err) => return Err(err),
+    };
+    if message.id.is_empty() {
+        message.id = new_id();
+    }
+    if message.created_at == 0 {
+        message.created_at = now;
+    }
+    if conversation.title == DEFAULT_TITLE && message.role == ChatRole::User {
+        if let Some(title) = title_from(&message.content) {
+            conversation.title = title;
+        }
+    }
+    if message.model.is_some() {
+        conversation.model = message.model.clone();
+    }
+    conversation.updated_at = now;
+    conversation.messages.push(message.clone());
+    write_conversation(app, &conversation)?;
+    Ok(message)
+}
+
+#[tauri::command]
+pub fn create_conversation(
+    app: AppHandle,
+    store: State<HistoryStore>,
+    title: Option<String>,
+    model: Option<String>,
+) -> Result<ConversationSummary, String> {
+    let _guard = store.lock.lock().unwrap_or_else(|err| err.into_inner());
+    let now = now_millis();
+    let title = title
+        .map(|title| title.trim().to_string())
+        .filter(|title| !title.is_empty())
+        .unwrap_or_else(|| DEFAULT_TITLE.to_string());
+    let conversation = Conversation {
+        id: new_id(),
+        title,
+        created_at: now,
+        updated_at: now,
+        model,
+        messages: Vec::new(),
+    };
+    write_conversation(&app, &conversation)?;
+    Ok(summarize(&conversation))
+}
+
+#[tauri::command]
+pub fn list_conversations(app: AppHandle) -> Result<Vec<ConversationSummary>, String> {
+    let mut summaries = read_all(&app)?.iter().map(summarize).collect::<Vec<_>>();
+    summaries.sort_by_key(|summary| std::cmp::Reverse(summary.updated_at));
+    Ok(summaries)
+}
+
+#[tauri::command]
+pub fn load_conversation(app: AppHandle, id: String) -> Result<Conversation, String> {
+    read_conversation(&app, &id)
+}
+
+#[tauri::command]
+pub fn append_conversation_message(
+    app: AppHandle,
+    conversation_id: String,
+    message: HistoryMessage,
+) -> Result<HistoryMessage, String> {
+    append_message(&app, &conversation_id, message)
+}
+
+#[tauri::command]
+pub fn rename_conversation(
+    app: AppHandle,
+    store: State<HistoryStore>,
+    id: String,
+    title: String,
+) -> Result<ConversationSummary, String> {
+    let trimmed = title.trim();
+    if trimmed.is_empty() {
+        return Err("Title is required.".to_string());
+    }
+    let _guard = store.lock.lock().unwrap_or_else(|err| err.into_inner());
+    let mut conversation = read_conversation(&app, &id)?;
+    conversation.title = trimmed.to_string();
+    conversation.updated_at = now_millis();
+    write_conversation(&app, &conversation)?;
+    Ok(summarize(&conversation))
+}
+
+#[tauri::command]
+pub fn delete_conversation(
+    app: AppHandle,
+    store: State<HistoryStore>,
+    id: String,
+) -> Result<(), String> {
+    let _guard = store.lock.lock().unwrap_or_else(|err| err.into_inner());
+    let path = conversation_path(&app, &id)?;
+    match fs::remove_file(path) {
+        Ok(()) => Ok(()),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(err) => Err(format!("Failed to delete conversation: {err}")),
+    }
+}
+
+#[tauri::command]
+pub fn search_conversations(
+    app: AppHandle,
+    query: String,
+    limit: Option<usize>,
+) -> Result<Vec<HistorySearchHit>, String> {
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+    let limit = limit.unwrap_or(DEFAULT_SEARCH_LIMIT).max(1);
+    let mut conversations = read_all(&app)?;
+    conversations.sort_by_key(|conversation| std::cmp::Reverse(conversation.updated_at));
+
+    let mut hits = Vec::new();
+    for conversation in &conversations {
+        if conversation.title.to_lowercase().contains(&query) {
+            hits.push(HistorySearchHit {
+                conversation_id: conversation.id.clone(),
+                title: conversation.title.clone(),
+                message_id: None,
+                snippet: conversation.title.clone(),
+            });
+        }
+        for message in &conversation.messages {
+            if message.content.to_lowercase().contains(&query) {
+                hits.push(HistorySearchHit {
+                    conversation_id: conversation.id.clone(),
+                    title: conversation.title.clone(),
+                    message_id: Some(message.id.clone()),
+                    snippet: snippet(&message.content, &query),
+                });
+            }
+        }
+        if hits.len() >= limit {
+            break;
+        }
+    }
+    hits.truncate(limit);
+    Ok(hits)
+}
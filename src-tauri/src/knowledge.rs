@@ -0,0 +1,527 @@
+use copilot_client::ollama::OllamaEndpoint;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::UNIX_EPOCH;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::config::{self, KnowledgeConfig};
+use crate::{files, ollama};
+
+// Retrieval over user documents. Text files in the configured folders are
+// chunked, embedded with Ollama's `/api/embed`, and kept in one JSON index under
+// the app data dir. Re-indexing only re-embeds files whose mtime changed.
+
+const INDEX_FILE: &str = "knowledge_index.json";
+// Larger folders are indexed partially rather than stalling the app.
+const MAX_FILES_PER_FOLDER: usize = 5_000;
+const EMBED_BATCH: usize = 16;
+// Save progress periodically so an interrupted run keeps finished files.
+const SAVE_EVERY_FILES: usize = 50;
+const DEFAULT_SEARCH_RESULTS: u32 = 5;
+const MAX_SEARCH_RESULTS: u32 = 20;
+// Dependency and build output folders rarely hold documentation.
+const SKIPPED_DIRS: &[&str] = &["node_modules", "target", "dist", "build", "__pycache__"];
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct IndexedChunk {
+    start_line: usize,
+    text: String,
+    embedding: Vec<f32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct IndexedFile {
+    // Milliseconds since the Unix epoch.
+    mtime: u64,
+    chunks: Vec<IndexedChunk>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+struct KnowledgeIndex {
+    // Vectors from different models aren't comparable, so a change rebuilds the index.
+    model: String,
+    files: HashMap<String, IndexedFile>,
+}
+
+/// Holds the loaded index. Indexing works on a copy and swaps it in when done,
+/// so searches never wait for a run (or for Ollama) to finish.
+#[derive(Default)]
+pub struct KnowledgeStore {
+    index: Mutex<Option<Arc<KnowledgeIndex>>>,
+    // Keeps indexing runs from overlapping.
+    indexing: tokio::sync::Mutex<()>,
+}
+
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct IndexReport {
+    pub folder: String,
+    pub indexed: usize,
+    pub unchanged: usize,
+    pub removed: usize,
+    // Binary, oversized or unreadable files.
+    pub skipped: usize,
+    // Files the embedding model rejected or couldn't be reached for; retried next run.
+    pub failed: Vec<String>,
+    pub chunks: usize,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct KnowledgeHit {
+    pub path: String,
+    pub start_line: usize,
+    pub text: String,
+    pub score: f32,
+}
+
+/// Emitted as `knowledge:progress` while a folder is indexed.
+#[derive(Debug, Serialize, Clone)]
+struct IndexProgress {
+    folder: String,
+    processed: usize,
+    total: usize,
+}
+
+fn index_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|err| format!("Failed to locate data dir: {err}"))?;
+    fs::create_dir_all(&dir).map_err(|err| format!("Failed to create data dir: {err}"))?;
+    Ok(dir.join(INDEX_FILE))
+}
+
+fn read_index(app: &AppHandle) -> Result<KnowledgeIndex, String> {
+    let path = index_path(app)?;
+    let Ok(contents) = fs::read_to_string(&path) else {
+        return Ok(KnowledgeIndex::default());
+    };
+    // A corrupt index can always be rebuilt from the source files.
+    Ok(serde_json::from_str(&contents).unwrap_or_else(|err| {
+        eprintln!("Resetting corrupt knowledge index: {err}");
+        KnowledgeIndex::default()
+    }))
+}
+
+fn write_index(app: &AppHandle, index: &KnowledgeIndex) -> Result<(), String> {
+    let path = index_path(app)?;
+    let payload = serde_json::to_string(index)
+        .map_err(|err| format!("Failed to encode knowledge index: {err}"))?;
+    let temp = path.with_extension("json.tmp");
+    fs::write(&temp, payload).map_err(|err| format!("Failed to write knowledge index: {err}"))?;
+    fs::rename(&temp, &path).map_err(|err| format!("Failed to save knowledge index: {err}"))
+}
+
+// The current index, loaded on first use and reset if the embedding model changed.
+fn snapshot(app: &AppHandle, model: &str) -> Result<Arc<KnowledgeIndex>, String> {
+    let store = app.state::<KnowledgeStore>();
+    let mut slot = store.index.lock().unwrap_or_else(|err| err.into_inner());
+    let index = match slot.take() {
+        Some(index) => index,
+        None => Arc::new(read_index(app)?),
+    };
+    let index = if index.model == model {
+        index
+    } else {
+        if !index.files.is_empty() {
+            eprintln!(
+                "Embedding model changed from {} to {model}; rebuilding knowledge index",
+                index.model
+            );
+        }
+        Arc::new(KnowledgeIndex {
+            model: model.to_string(),
+            files: HashMap::new(),
+        })
+    };
+    *slot = Some(index.clone());
+    Ok(index)
+}
+
+// Saves an indexing run's result and makes it visible to searches.
+fn publish(app: &AppHandle, index: KnowledgeIndex) -> Result<(), String> {
+    let saved = write_index(app, &index);
+    let store = app.state::<KnowledgeStore>();
+    *store.index.lock().unwrap_or_else(|err| err.into_inner()) = Some(Arc::new(index));
+    saved
+}
+
+fn mtime_millis(path: &Path) -> Option<u64> {
+    let modified = fs::metadata(path).ok()?.modified().ok()?;
+    Some(modified.duration_since(UNIX_EPOCH).ok()?.as_millis() as u64)
+}
+
+fn list_files(folder: &Path) -> Vec<PathBuf> {
+    let mut found = Vec::new();
+    let mut pending = vec![folder.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            if name.starts_with('.') {
+                continue;
+            }
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            if file_type.is_dir() && !SKIPPED_DIRS.contains(&name.as_ref()) {
+                pending.push(entry.path());
+            } else if file_type.is_file() {
+                found.push(entry.path());
+                if found.len() >= MAX_FILES_PER_FOLDER {
+                    eprintln!(
+                        "Indexing only {MAX_FILES_PER_FOLDER} files of {}",
+                        folder.display()
+                    );
+                    return found;
+                }
+            }
+        }
+    }
+    found
+}
+
+/// Splits text into chunks of about `chunk_chars` on line boundaries, with the
+/// trailing `overlap` characters repeated at the start of the next chunk.
+/// Returns each chunk with its 1-based starting line.
+fn chunk_text(text: &str, chunk_chars: usize, overlap: usize) -> Vec<(usize, String)> {
+    let chunk_chars = chunk_chars.max(1);
+    // Very long lines (minified files, single-paragraph documents) are cut into pieces.
+    let mut lines = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let chars = line.chars().collect::<Vec<_>>();
+        if chars.is_empty() {
+            lines.push((number + 1, String::new()));
+        }
+        for piece in chars.chunks(chunk_chars) {
+            lines.push((number + 1, piece.iter().collect::<String>()));
+        }
+    }
+    let width = |line: &(usize, String)| line.1.chars().count() + 1;
+
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    while start < lines.len() {
+        let mut end = start;
+        let mut size = 0;
+        while end < lines.len() && (end == start || size + width(&lines[end]) <= chunk_chars) {
+            size += width(&lines[end]);
+            end += 1;
+        }
+        let text = lines[start..end]
+            .iter()
+            .map(|(_, line)| line.as_str())
+            .collect::<Vec<_>>()
+            .join("\n");
+        if !text.trim().is_empty() {
+            chunks.push((lines[start].0, text));
+        }
+        if end >= lines.len() {
+            break;
+        }
+        // Step back for the overlap, but always move forward.
+        let mut next = end;
+        let mut kept = 0;
+        while next > start + 1 && kept + width(&lines[next - 1]) <= overlap {
+            kept += width(&lines[next - 1]);
+            next -= 1;
+        }
+        start = next;
+    }
+    chunks
+}
+
+async fn embed_text(
+    endpoint: &OllamaEndpoint,
+    settings: &KnowledgeConfig,
+    content: &str,
+) -> Result<Vec<IndexedChunk>, String> {
+    let pieces = chunk_text(
+        content,
+        settings.chunk_chars as usize,
+        settings.chunk_overlap as usize,
+    );
+    let mut chunks = Vec::with_capacity(pieces.len());
+    for batch in pieces.chunks(EMBED_BATCH) {
+        let inputs = batch
+            .iter()
+            .map(|(_, text)| text.clone())
+            .collect::<Vec<_>>();
+        let embeddings = endpoint.embed(&settings.embedding_model, &inputs).await?;
+        for ((start_line, text), embedding) in batch.iter().cloned().zip(embeddings) {
+            chunks.push(IndexedChunk {
+                start_line,
+                text,
+                embedding,
+            });
+        }
+    }
+    Ok(chunks)
+}
+
+async fn index_one(
+    app: &AppHandle,
+    endpoint: &OllamaEndpoint,
+    settings: &KnowledgeConfig,
+    index: &mut KnowledgeIndex,
+    folder: &Path,
+) -> Result<IndexReport, String> {
+    let folder_name = folder.to_string_lossy().into_owned();
+    let mut report = IndexReport {
+        folder: folder_name.clone(),
+        ..IndexReport::default()
+    };
+    let paths = list_files(folder);
+    let mut seen = HashSet::new();
+    for (position, path) in paths.iter().enumerate() {
+        let key = path.to_string_lossy().into_owned();
+        seen.insert(key.clone());
+        let Some(mtime) = mtime_millis(path) else {
+            report.skipped += 1;
+            continue;
+        };
+        if index
+            .files
+            .get(&key)
+            .is_some_and(|file| file.mtime == mtime)
+        {
+            report.unchanged += 1;
+        } else {
+            match files::read_text_file(path) {
+                Ok((content, _)) => {
+                    // The caller publishes finished files, so the next run resumes from here.
+                    match embed_text(endpoint, settings, &content).await {
+                        Ok(chunks) => {
+                            report.indexed += 1;
+                            report.chunks += chunks.len();
+                            index.files.insert(key, IndexedFile { mtime, chunks });
+                            if report.indexed.is_multiple_of(SAVE_EVERY_FILES) {
+                                write_index(app, index)?;
+                            }
+                        }
+                        // Any earlier entry keeps its old mtime, so the next run retries it.
+                        Err(err) => {
+                            eprintln!("Failed to embed {}: {err}", path.display());
+                            report.failed.push(key);
+                        }
+                    }
+                }
+                // Binary or too large; forget any earlier version of it.
+                Err(_) => {
+                    report.skipped += 1;
+                    index.files.remove(&key);
+                }
+            }
+        }
+        let _ = app.emit(
+            "knowledge:progress",
+            IndexProgress {
+                folder: folder_name.clone(),
+                processed: position + 1,
+                total: paths.len(),
+            },
+        );
+    }
+
+    let stale = index
+        .files
+        .keys()
+        .filter(|key| Path::new(key).starts_with(folder) && !seen.contains(*key))
+        .cloned()
+        .collect::<Vec<_>>();
+    report.removed = stale.len();
+    for key in stale {
+        index.files.remove(&key);
+    }
+    Ok(report)
+}
+
+fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    let (mut dot, mut norm_a, mut norm_b) = (0.0, 0.0, 0.0);
+    for (x, y) in a.iter().zip(b) {
+        dot += x * y;
+        norm_a += x * x;
+        norm_b += y * y;
+    }
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+    dot / (norm_a.sqrt() * norm_b.sqrt())
+}
+
+/// Returns the chunks closest to `query`; shared by the command and the agent tool.
+pub async fn search(
+    app: &AppHandle,
+    query: &str,
+    max_results: Option<u32>,
+) -> Result<Vec<KnowledgeHit>, String> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Err("Search query is required.".into());
+    }
+    let settings = config::load_overlay_config(app).knowledge;
+    let index = snapshot(app, &settings.embedding_model)?;
+    if index.files.is_empty() {
+        return Err("The knowledge index is empty. Index a folder first.".into());
+    }
+    let endpoint = ollama::resolve_endpoint(app)?;
+    let embedding = endpoint
+        .embed(&settings.embedding_model, &[query.to_string()])
+        .await?
+        .pop()
+        .unwrap_or_default();
+
+    let mut hits = index
+        .files
+        .iter()
+        .flat_map(|(path, file)| {
+            file.chunks.iter().map(|chunk| KnowledgeHit {
+                path: path.clone(),
+                start_line: chunk.start_line,
+                text: chunk.text.clone(),
+                score: cosine(&embedding, &chunk.embedding),
+            })
+        })
+        .collect::<Vec<_>>();
+    hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+    let limit = max_results
+        .unwrap_or(DEFAULT_SEARCH_RESULTS)
+        .clamp(1, MAX_SEARCH_RESULTS);
+    hits.truncate(limit as usize);
+    Ok(hits)
+}
+
+/// Indexes a folder and adds it to `knowledge.folders` so `reindex` keeps it fresh.
+#[tauri::command]
+pub async fn index_folder(app: AppHandle, path: String) -> Result<IndexReport, String> {
+    let folder =
+        fs::canonicalize(path.trim()).map_err(|err| format!("Unable to open folder: {err}"))?;
+    if !folder.is_dir() {
+        return Err("Path is not a folder.".into());
+    }
+    let mut config = config::load_overlay_config(&app);
+    let folder_name = folder.to_string_lossy().into_owned();
+    if !config.knowledge.folders.contains(&folder_name) {
+        config.knowledge.folders.push(folder_name);
+        config::save_overlay_config(&app, &config);
+    }
+
+    let endpoint = ollama::resolve_endpoint(&app)?;
+    let store = app.state::<KnowledgeStore>();
+    let _running = store.indexing.lock().await;
+    let mut index = (*snapshot(&app, &config.knowledge.embedding_model)?).clone();
+    let result = index_one(&app, &endpoint, &config.knowledge, &mut index, &folder).await;
+    publish(&app, index)?;
+    result.inspect_err(|err| eprintln!("Knowledge indexing failed: {err}"))
+}
+
+/// Refreshes every configured folder and drops files from folders no longer listed.
+#[tauri::command]
+pub async fn reindex(app: AppHandle) -> Result<Vec<IndexReport>, String> {
+    let settings = config::load_overlay_config(&app).knowledge;
+    let endpoint = ollama::resolve_endpoint(&app)?;
+    let store = app.state::<KnowledgeStore>();
+    let _running = store.indexing.lock().await;
+    let mut index = (*snapshot(&app, &settings.embedding_model)?).clone();
+
+    let folders = settings
+        .folders
+        .iter()
+        .map(PathBuf::from)
+        .collect::<Vec<_>>();
+    index.files.retain(|key, _| {
+        folders
+            .iter()
+            .any(|folder| Path::new(key).starts_with(folder))
+    });
+    let mut reports = Vec::with_capacity(folders.len());
+    let mut result = Ok(());
+    for folder in &folders {
+        match index_one(&app, &endpoint, &settings, &mut index, folder).await {
+            Ok(report) => reports.push(report),
+            Err(err) => {
+                result = Err(err);
+                break;
+            }
+        }
+    }
+    publish(&app, index)?;
+    result.inspect_err(|err| eprintln!("Knowledge reindex failed: {err}"))?;
+    Ok(reports)
+}
+
+#[tauri::command]
+pub async fn search_knowledge(
+    app: AppHandle,
+    query: String,
+    max_results: Option<u32>,
+) -> Result<Vec<KnowledgeHit>, String> {
+    search(&app, &query, max_results)
+        .await
+        .inspect_err(|err| eprintln!("Knowledge search failed: {err}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunks_on_line_boundaries_with_start_lines() {
+        let text = "alpha\nbravo\ncharlie\ndelta";
+        let chunks = chunk_text(text, 14, 0);
+        assert_eq!(
+            chunks,
+            [
+                (1, "alpha\nbravo".to_string()),
+                (3, "charlie\ndelta".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn repeats_trailing_lines_as_overlap() {
+        let text = "alpha\nbravo\ncharlie\ndelta";
+        let chunks = chunk_text(text, 14, 8);
+        assert_eq!(
+            chunks,
+            [
+                (1, "alpha\nbravo".to_string()),
+                (2, "bravo\ncharlie".to_string()),
+                (3, "charlie\ndelta".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn overlap_at_least_chunk_size_still_advances() {
+        let text = (1..=20)
+            .map(|n| format!("line {n}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let chunks = chunk_text(&text, 16, 64);
+        assert_eq!(chunks.first().map(|chunk| chunk.0), Some(1));
+        assert_eq!(chunks.last().map(|chunk| chunk.0), Some(19));
+        assert!(chunks.windows(2).all(|pair| pair[0].0 < pair[1].0));
+    }
+
+    #[test]
+    fn splits_long_lines_and_skips_blank_chunks() {
+        let chunks = chunk_text("\n\nabcdefghij", 4, 0);
+        assert_eq!(
+            chunks,
+            [
+                (3, "abcd".to_string()),
+                (3, "efgh".to_string()),
+                (3, "ij".to_string()),
+            ]
+        );
+    }
+}
@@ -1,14 +1,30 @@
 // Prevents additional console window on Windows in release DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod agent;
 mod capture;
 mod clipboard;
+mod compare;
 mod config;
+mod context;
 mod files;
+mod health;
+mod history;
+mod knowledge;
+mod metrics;
 mod ollama;
 mod overlay;
+mod provider;
+mod scheduler;
+mod search;
 mod secrets;
+mod server;
 mod shortcuts;
+mod sink;
+mod structured;
+mod vision;
+mod warmup;
+mod web;
 use tauri::Manager;
 use tauri::{
     menu::{Menu, MenuItem},
@@ -51,6 +67,16 @@ fn main() {
             let config = config::load_overlay_config(&handle);
             // Keep overlay state in memory for snapping and restoring position.
             app.manage(overlay::OverlayState::new(config.corner));
+            app.manage(ollama::StreamRegistry::default());
+            app.manage(history::HistoryStore::default());
+            app.manage(metrics::MetricsStore::default());
+            app.manage(context::ContextCache::default());
+            app.manage(vision::CapabilityCache::default());
+            app.manage(knowledge::KnowledgeStore::default());
+            app.manage(scheduler::RequestScheduler::default());
+            app.manage(warmup::ModelWarmer::default());
+            warmup::listen_for_overlay(&handle);
+            app.manage(server::OllamaSupervisor::default());
             config::save_overlay_config(&handle, &config);
 
             shortcuts::register_overlay_shortcut(&handle, &config);
@@ -68,8 +94,12 @@ fn main() {
                     }
                 });
             }
-            // Run a background health check so the UI can prompt if Ollama is missing.
-            tauri::async_runtime::spawn(ollama::emit_health_if_needed(app.handle().clone()));
+            if config.local_server.managed {
+                // Failures are recorded in the supervisor status; the health modal still shows.
+                let _ = server::start(&handle);
+            }
+            // Monitor Ollama in the background so the UI can prompt when it goes away or returns.
+            tauri::async_runtime::spawn(health::run_health_monitor(app.handle().clone()));
 
             // Create the tray icon and menu shortcuts.
             let preferences_i =
@@ -110,7 +140,9 @@ fn main() {
                         let _ = preferences_window;
                     }
                     // adds the event for the quit menu uitem
-                    "quit" => app.exit(0),
+                    "quit" => {
+                        app.exit(0);
+                    }
                     _ => {}
                 })
                 .build(app)?;
@@ -130,13 +162,51 @@ fn main() {
             clipboard::read_clipboard_text,
             files::read_file,
             ollama::ollama_health_check,
+            metrics::get_model_stats,
+            server::start_ollama_server,
+            server::stop_ollama_server,
+            server::ollama_server_status,
+            server::ollama_server_logs,
             ollama::ollama_chat,
-            ollama::ollama_web_search,
+            search::ollama_web_search,
+            web::fetch_url,
+            compare::ollama_chat_compare,
+            knowledge::index_folder,
+            knowledge::reindex,
+            knowledge::search_knowledge,
+            agent::ollama_agent_chat,
+            history::create_conversation,
+            history::list_conversations,
+            history::load_conversation,
+            history::append_conversation_message,
+            history::rename_conversation,
+            history::delete_conversation,
+            history::search_conversations,
             ollama::ollama_chat_stream,
+            structured::ollama_chat_structured,
+            ollama::cancel_chat_stream,
+            ollama::ollama_list_models,
+            ollama::ollama_show_model,
+            ollama::ollama_delete_model,
+            warmup::ollama_unload_model,
+            ollama::ollama_pull_model,
             secrets::get_ollama_web_search_key_status,
             secrets::set_ollama_web_search_api_key,
             secrets::clear_ollama_web_search_api_key,
+            secrets::get_ollama_endpoint_auth_status,
+            secrets::set_ollama_endpoint_auth_header,
+            secrets::clear_ollama_endpoint_auth_header,
+            secrets::get_provider_api_key_status,
+            secrets::set_provider_api_key,
+            secrets::clear_provider_api_key,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            // Covers every way out (tray Quit, last window closed, OS logout),
+            // so a managed `ollama serve` never outlives the app.
+            if let tauri::RunEvent::Exit = event {
+                server::shutdown(app);
+            }
+        });
 }
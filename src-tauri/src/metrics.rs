@@ -0,0 +1,120 @@
+use copilot_client::metrics::GenerationMetrics;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+
+// Rolling per-model performance stats, stored next to the history directory
+// so users can compare which models are actually usable on their hardware.
+
+const STATS_FILE: &str = "model_stats.json";
+// Averages are computed over this many recent generations.
+const WINDOW: usize = 50;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ModelStats {
+    pub model: String,
+    // Total generations ever recorded, not just the window.
+    pub samples: u64,
+    pub avg_tokens_per_second: Option<f64>,
+    pub avg_prompt_tokens_per_second: Option<f64>,
+    pub avg_time_to_first_token_ms: Option<f64>,
+    pub avg_load_ms: Option<f64>,
+    pub last: Option<GenerationMetrics>,
+    #[serde(default)]
+    pub recent: VecDeque<GenerationMetrics>,
+    pub updated_at: u64,
+}
+
+#[derive(Default)]
+pub struct MetricsStore {
+    lock: Mutex<()>,
+}
+
+fn average(values: impl Iterator<Item = Option<f64>>) -> Option<f64> {
+    let (sum, count) = values.flatten().fold((0.0, 0usize), |(sum, count), value| {
+        (sum + value, count + 1)
+    });
+    (count > 0).then(|| sum / count as f64)
+}
+
+impl ModelStats {
+    fn push(&mut self, metrics: GenerationMetrics) {
+        if self.recent.len() >= WINDOW {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(metrics.clone());
+        self.samples += 1;
+        self.avg_tokens_per_second = average(self.recent.iter().map(|m| m.tokens_per_second));
+        self.avg_prompt_tokens_per_second =
+            average(self.recent.iter().map(|m| m.prompt_tokens_per_second));
+        self.avg_time_to_first_token_ms =
+            average(self.recent.iter().map(|m| m.time_to_first_token_ms));
+        self.avg_load_ms = average(self.recent.iter().map(|m| m.load_ms));
+        self.updated_at = metrics.created_at;
+        self.last = Some(metrics);
+    }
+}
+
+fn stats_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|err| format!("Failed to locate data dir: {err}"))?;
+    fs::create_dir_all(&dir).map_err(|err| format!("Failed to create data dir: {err}"))?;
+    Ok(dir.join(STATS_FILE))
+}
+
+fn read_stats(app: &AppHandle) -> Result<HashMap<String, ModelStats>, String> {
+    let path = stats_path(app)?;
+    let Ok(contents) = fs::read_to_string(&path) else {
+        return Ok(HashMap::new());
+    };
+    // A corrupt stats file only loses history; start over instead of failing.
+    Ok(serde_json::from_str(&contents).unwrap_or_else(|err| {
+        eprintln!("Resetting corrupt model stats: {err}");
+        HashMap::new()
+    }))
+}
+
+fn write_stats(app: &AppHandle, stats: &HashMap<String, ModelStats>) -> Result<(), String> {
+    let path = stats_path(app)?;
+    let payload = serde_json::to_string_pretty(stats)
+        .map_err(|err| format!("Failed to encode model stats: {err}"))?;
+    let temp = path.with_extension("json.tmp");
+    fs::write(&temp, payload).map_err(|err| format!("Failed to write model stats: {err}"))?;
+    fs::rename(&temp, &path).map_err(|err| format!("Failed to save model stats: {err}"))
+}
+
+/// Adds one generation to the model's rolling stats.
+pub fn record(app: &AppHandle, metrics: &GenerationMetrics) {
+    let store = app.state::<MetricsStore>();
+    let _guard = store.lock.lock().unwrap_or_else(|err| err.into_inner());
+    let result = read_stats(app).and_then(|mut stats| {
+        let entry = stats
+            .entry(metrics.model.clone())
+            .or_insert_with(|| ModelStats {
+                model: metrics.model.clone(),
+                ..ModelStats::default()
+            });
+        entry.push(metrics.clone());
+        write_stats(app, &stats)
+    });
+    if let Err(err) = result {
+        eprintln!("Failed to record model stats: {err}");
+    }
+}
+
+#[tauri::command]
+pub fn get_model_stats(app: AppHandle, model: Option<String>) -> Result<Vec<ModelStats>, String> {
+    let store = app.state::<MetricsStore>();
+    let _guard = store.lock.lock().unwrap_or_else(|err| err.into_inner());
+    let mut stats = read_stats(&app)?
+        .into_values()
+        .filter(|entry| model.as_ref().is_none_or(|name| &entry.model == name))
+        .collect::<Vec<_>>();
+    stats.sort_by(|a, b| a.model.cmp(&b.model));
+    Ok(stats)
+}
@@ -1,310 +1,564 @@
 use base64::Engine;
+use copilot_client::chat::{ChatError, ChatRequest, ChatResponse, ChatRole};
+use copilot_client::http::describe_reqwest_error;
+use copilot_client::ollama::{
+    next_ndjson_line, OllamaEndpoint, OllamaGenerationStats, OllamaModelInfo, OllamaModelList,
+};
+use copilot_client::provider::ChatProvider;
+use copilot_client::stream::{
+    self, OllamaStreamPayload, StreamEventKind, StreamOutcome, StreamSession, StreamSink,
+};
 use futures_util::StreamExt;
+use percent_encoding::percent_decode_str;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::collections::HashMap;
 use std::path::Path;
+use std::sync::Mutex;
 use std::time::Duration;
-use tauri::{AppHandle, Emitter};
+use tauri::ipc::JavaScriptChannelId;
+use tauri::{AppHandle, Emitter, Manager, Webview};
+use tokio::sync::oneshot;
 
-use crate::secrets;
+use crate::history::{self, HistoryMessage};
+use crate::provider::{self, Provider};
+use crate::scheduler::{self, RequestPriority};
+use crate::{capture, config, context, metrics, secrets, sink, vision, warmup};
 
-// Keep this local-only; the frontend never calls Ollama directly.
-const OLLAMA_BASE_URL: &str = "http://localhost:11434";
-const OLLAMA_WEB_SEARCH_API_URL: &str = "https://ollama.com/api/web_search";
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct OllamaHealthPayload {
-    pub ok: bool,
-    pub error: Option<String>,
-}
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct OllamaWebSearchResponse {
-    pub results: Vec<OllamaWebSearchResult>,
+// The frontend never calls Ollama directly; every request goes through the
+// endpoint from `resolve_endpoint`. Resolved per call so config changes apply
+// without a restart.
+pub fn resolve_endpoint(app: &AppHandle) -> Result<OllamaEndpoint, String> {
+    let endpoint = config::load_overlay_config(app).endpoint;
+    let base_url = endpoint.base_url.trim().trim_end_matches('/').to_string();
+    if !base_url.starts_with("http://") && !base_url.starts_with("https://") {
+        return Err(format!("Invalid Ollama base URL: '{base_url}'"));
+    }
+    let auth_header = if endpoint.use_auth_header {
+        secrets::load_endpoint_auth_header()?
+    } else {
+        None
+    };
+    Ok(OllamaEndpoint::new(
+        base_url,
+        auth_header,
+        endpoint.verify_tls,
+    ))
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct OllamaWebSearchResult {
-    pub content: String,
+// Turns a `file://` URI into a local path, e.g. `file:///C:/a%20b.png` -> `C:/a b.png`.
+fn file_uri_to_path(uri: &str) -> String {
+    let rest = uri.strip_prefix("localhost").unwrap_or(uri);
+    let decoded = percent_decode_str(rest).decode_utf8_lossy().into_owned();
+    let bytes = decoded.as_bytes();
+    // Windows drive paths arrive as `/C:/...`.
+    if bytes.len() > 2 && bytes[0] == b'/' && bytes[1].is_ascii_alphabetic() && bytes[2] == b':' {
+        return decoded[1..].to_string();
+    }
+    decoded
 }
 
-#[derive(Debug, Serialize)]
-struct OllamaWebSearchRequest {
-    query: String,
-    #[serde(skip_serializing_if = "Option::is_none", rename = "maxResults")]
-    max_results: Option<u32>,
+fn decode_data_uri(uri: &str) -> Result<Vec<u8>, String> {
+    let (header, data) = uri
+        .split_once(',')
+        .ok_or("Malformed data: URI (missing ',').")?;
+    if !header.ends_with(";base64") {
+        return Err("Only base64 data: URIs are supported for images.".into());
+    }
+    base64::engine::general_purpose::STANDARD
+        .decode(data.trim())
+        .map_err(|err| format!("Invalid base64 in data: URI: {err}"))
 }
 
-fn build_client() -> Result<reqwest::Client, String> {
-    reqwest::Client::builder()
-        .timeout(Duration::from_secs(32))
-        .build()
-        .map_err(|err| format!("HTTP client error: {err}"))
+// Already-encoded payloads from the webview. Paths almost always contain a '.',
+// a backslash, or whitespace, none of which appear in base64.
+fn decode_raw_base64(value: &str) -> Option<Vec<u8>> {
+    if value.len() < 64 || value.contains(['.', '\\', ' ']) {
+        return None;
+    }
+    base64::engine::general_purpose::STANDARD.decode(value).ok()
 }
 
-fn describe_reqwest_error(err: &reqwest::Error) -> String {
-    // Normalize common failure modes for the UI.
-    if err.is_timeout() {
-        return "timeout while connecting to Ollama".to_string();
+fn read_image_file(raw: &str, path: &str) -> Result<Vec<u8>, String> {
+    let mut path = path.to_string();
+    // Plain paths are taken literally, but accept percent-encoded ones too.
+    if !Path::new(&path).exists() && path.contains('%') {
+        path = percent_decode_str(&path).decode_utf8_lossy().into_owned();
     }
-    if err.is_connect() {
-        return "connection refused by Ollama".to_string();
+    if !Path::new(&path).is_file() {
+        return Err(format!("Image file not found: '{}'", image_label(raw)));
     }
-    format!("request error: {err}")
+    std::fs::read(&path).map_err(|err| format!("Failed to read image file '{path}': {err}"))
 }
 
-fn encode_image_path(raw: &str) -> Result<Option<String>, String> {
-    if raw.is_empty() {
-        return Ok(None);
-    }
+/// Resolves one `images` entry (path, `file://` URI, `data:` URI, or raw base64)
+/// to base64 the model can read.
+fn encode_image_path(raw: &str) -> Result<String, String> {
     let trimmed = raw.trim();
-    if trimmed.is_empty() {
-        return Ok(None);
-    }
-    let mut normalized = trimmed;
-    if let Some(stripped) = trimmed.strip_prefix("file://") {
-        normalized = stripped;
-    }
-    let normalized = normalized.replace("%20", " ");
-    let path = Path::new(&normalized);
-    if !path.exists() {
-        return Ok(None);
+    let bytes = if let Some(uri) = trimmed.strip_prefix("data:") {
+        decode_data_uri(uri)?
+    } else if let Some(uri) = trimmed.strip_prefix("file://") {
+        read_image_file(raw, &file_uri_to_path(uri))?
+    } else if let Some(bytes) = decode_raw_base64(trimmed) {
+        bytes
+    } else {
+        read_image_file(raw, trimmed)?
+    };
+    let prepared = capture::prepare_model_image(&bytes)
+        .map_err(|err| format!("Unusable image '{}': {err}", image_label(raw)))?;
+    Ok(base64::engine::general_purpose::STANDARD.encode(prepared))
+}
+
+// Keeps error messages readable when the entry is a large inline payload.
+fn image_label(raw: &str) -> String {
+    let trimmed = raw.trim();
+    if trimmed.len() > 80 {
+        format!("{}…", trimmed.chars().take(40).collect::<String>())
+    } else {
+        trimmed.to_string()
     }
-    let bytes = std::fs::read(path)
-        .map_err(|err| format!("Failed to read image file '{normalized}': {err}"))?;
-    Ok(Some(
-        base64::engine::general_purpose::STANDARD.encode(bytes),
-    ))
 }
 
-fn normalize_image_paths(payload: &mut Value) -> Result<(), String> {
-    let Some(messages) = payload.get_mut("messages").and_then(Value::as_array_mut) else {
-        return Ok(());
-    };
-    for message in messages.iter_mut() {
-        let Some(images) = message.get_mut("images").and_then(Value::as_array_mut) else {
-            continue;
-        };
-        for image in images.iter_mut() {
-            let Some(raw) = image.as_str() else {
-                continue;
-            };
-            if let Some(encoded) = encode_image_path(raw)? {
-                *image = Value::String(encoded);
-            }
+pub fn normalize_image_paths(request: &mut ChatRequest) -> Result<(), String> {
+    for message in request.messages.iter_mut() {
+        // Ollama rejects the whole request over one invalid image.
+        message.images.retain(|image| !image.trim().is_empty());
+        for image in message.images.iter_mut() {
+            *image = encode_image_path(image)?;
         }
     }
     Ok(())
 }
 
-async fn check_ollama_health() -> Result<(), String> {
-    let client = build_client()?;
-    let url = format!("{}/api/tags", OLLAMA_BASE_URL);
-    let response = client.get(url).send().await.map_err(|err| {
-        let detail = describe_reqwest_error(&err);
-        eprintln!("Ollama health check failed: {detail}");
-        detail
-    })?;
-
-    if !response.status().is_success() {
-        let status = response.status();
-        let body = response.text().await.unwrap_or_default();
-        let detail = format!("non-200 from Ollama: {status} {body}");
-        eprintln!("Ollama health check failed: {detail}");
-        return Err(detail);
-    }
+pub async fn check_ollama_health(app: &AppHandle) -> Result<(), String> {
+    Provider::resolve(app)?.health().await
+}
 
-    Ok(())
+#[tauri::command]
+pub async fn ollama_health_check(app: AppHandle) -> Result<(), String> {
+    check_ollama_health(&app).await
 }
 
-pub async fn emit_health_if_needed(app: AppHandle) {
-    // Only emit on failure to avoid spamming the UI.
-    if let Err(err) = check_ollama_health().await {
-        let payload = OllamaHealthPayload {
-            ok: false,
-            error: Some(err),
-        };
-        let _ = app.emit("ollama:health", payload);
+#[tauri::command]
+pub async fn ollama_chat(
+    app: AppHandle,
+    request: Value,
+    priority: Option<RequestPriority>,
+) -> Result<ChatResponse, ChatError> {
+    let request = ChatRequest::from_value(request, &config::load_overlay_config(&app).models)?;
+    provider::send_chat_with_priority(&app, &request, priority.unwrap_or_default()).await
+}
+
+struct ActiveStream {
+    task: tauri::async_runtime::JoinHandle<()>,
+    // Kept so a cancelled stream can still report its end.
+    sink: StreamSink,
+}
+
+impl ActiveStream {
+    /// Abort the task and send the terminal chunk it can no longer send itself.
+    fn cancel(self, stream_id: &str) {
+        self.task.abort();
+        self.sink.send(OllamaStreamPayload::new(
+            stream_id,
+            StreamEventKind::Cancelled,
+        ));
     }
 }
 
-#[tauri::command]
-pub async fn ollama_health_check() -> Result<(), String> {
-    check_ollama_health().await
+/// In-flight chat streams keyed by `stream_id`, so they can be aborted.
+#[derive(Default)]
+pub struct StreamRegistry {
+    streams: Mutex<HashMap<String, ActiveStream>>,
 }
 
-#[tauri::command]
-pub async fn ollama_chat(request: Value) -> Result<Value, String> {
-    let client = build_client()?;
-    let mut payload = request;
-    normalize_image_paths(&mut payload)?;
-    let Some(obj) = payload.as_object_mut() else {
-        return Err("Invalid request payload.".to_string());
-    };
-    obj.insert("stream".to_string(), Value::Bool(false));
+impl StreamRegistry {
+    fn finish(&self, stream_id: &str) {
+        if let Ok(mut streams) = self.streams.lock() {
+            streams.remove(stream_id);
+        }
+    }
 
-    let url = format!("{}/api/chat", OLLAMA_BASE_URL);
-    let response = client
-        .post(url)
-        .json(&payload)
-        .send()
-        .await
-        .map_err(|err| {
-            let detail = describe_reqwest_error(&err);
-            eprintln!("Ollama chat request failed: {detail}");
-            detail
-        })?;
-
-    if !response.status().is_success() {
-        let status = response.status();
-        let body = response.text().await.unwrap_or_default();
-        let detail = format!("non-200 from Ollama: {status} {body}");
-        eprintln!("Ollama chat request failed: {detail}");
-        return Err(detail);
+    fn cancel(&self, stream_id: &str) -> bool {
+        let stream = self
+            .streams
+            .lock()
+            .ok()
+            .and_then(|mut streams| streams.remove(stream_id));
+        match stream {
+            Some(stream) => {
+                stream.cancel(stream_id);
+                true
+            }
+            None => false,
+        }
     }
 
-    response.json::<Value>().await.map_err(|err| {
-        let detail = format!("invalid Ollama response: {err}");
-        eprintln!("Ollama chat request failed: {detail}");
-        detail
-    })
+    fn active_ids(&self) -> Vec<String> {
+        self.streams
+            .lock()
+            .map(|streams| streams.keys().cloned().collect())
+            .unwrap_or_default()
+    }
 }
 
-#[tauri::command]
-pub async fn ollama_web_search(
-    query: String,
-    max_results: Option<u32>,
-) -> Result<OllamaWebSearchResponse, String> {
-    let client = build_client()?;
-    let api_key = secrets::load_web_search_api_key()?;
-    let payload = OllamaWebSearchRequest { query, max_results };
-    let response = client
-        .post(OLLAMA_WEB_SEARCH_API_URL)
-        .header(reqwest::header::AUTHORIZATION, format!("Bearer {api_key}"))
-        .header("X-API-Key", api_key)
-        .json(&payload)
-        .send()
-        .await
-        .map_err(|err| {
-            let detail = describe_reqwest_error(&err);
-            eprintln!("Ollama web search request failed: {detail}");
-            detail
-        })?;
-
-    if !response.status().is_success() {
-        let status = response.status();
-        let body = response.text().await.unwrap_or_default();
-        let detail = format!("non-200 from Ollama web search: {status} {body}");
-        eprintln!("Ollama web search request failed: {detail}");
-        return Err(detail);
+pub fn cancel_stream(app: &AppHandle, stream_id: &str) -> bool {
+    app.state::<StreamRegistry>().cancel(stream_id)
+}
+
+/// Abort every running chat stream; used by the `stop_generation` shortcut.
+pub fn cancel_active_streams(app: &AppHandle) {
+    let registry = app.state::<StreamRegistry>();
+    for stream_id in registry.active_ids() {
+        cancel_stream(app, &stream_id);
     }
+}
 
-    response
-        .json::<OllamaWebSearchResponse>()
-        .await
-        .map_err(|err| {
-            let detail = format!("invalid Ollama web search response: {err}");
-            eprintln!("Ollama web search request failed: {detail}");
-            detail
-        })
+#[tauri::command]
+pub fn cancel_chat_stream(app: AppHandle, stream_id: String) -> bool {
+    cancel_stream(&app, &stream_id)
 }
 
-#[derive(Debug, Serialize, Clone)]
-struct OllamaStreamPayload {
+/// Prepares `payload` and streams it to `sink` from a task registered under
+/// `stream_id`, so it can be cancelled or superseded.
+pub async fn start_stream(
+    app: &AppHandle,
+    provider: Provider,
+    mut payload: ChatRequest,
     stream_id: String,
-    chunk: Option<Value>,
-    error: Option<String>,
+    conversation_id: Option<String>,
+    priority: RequestPriority,
+    sink: StreamSink,
+) -> Result<oneshot::Receiver<StreamOutcome>, ChatError> {
+    normalize_image_paths(&mut payload)?;
+    vision::route_images(app, &provider, &mut payload, Some(&stream_id)).await?;
+    let context = context::fit_to_context(app, &provider, &mut payload).await;
+    if let Some(conversation_id) = &conversation_id {
+        scheduler::supersede(app, conversation_id, &stream_id);
+    }
+    warmup::remember_model(app, &payload.model);
+    let batch_interval = Duration::from_millis(u64::from(
+        config::load_overlay_config(app).streaming.batch_ms,
+    ));
+    let mut session = StreamSession::new(
+        stream_id.clone(),
+        payload.model.clone(),
+        context,
+        sink.clone(),
+        batch_interval,
+    );
+
+    let (report, outcome) = oneshot::channel();
+    let registry = app.state::<StreamRegistry>();
+    // Hold the lock while spawning so the task can't finish before it is registered.
+    let mut streams = registry
+        .streams
+        .lock()
+        .unwrap_or_else(|err| err.into_inner());
+    // A reused id replaces the old stream, which still gets its `Cancelled` chunk.
+    if let Some(previous) = streams.remove(&stream_id) {
+        previous.cancel(&stream_id);
+    }
+    let app_handle = app.clone();
+    let queued_id = stream_id.clone();
+    let handle = tauri::async_runtime::spawn(async move {
+        let history_id = conversation_id.clone();
+        // Queued here so the stream can be cancelled or superseded while waiting.
+        let queued =
+            scheduler::acquire(&app_handle, priority, Some(queued_id), conversation_id).await;
+        let result = match queued {
+            Ok(_permit) => {
+                stream::run_stream(
+                    &provider,
+                    &payload,
+                    &mut session,
+                    &mut |session, stats, metrics| {
+                        if let Some(metrics) = metrics {
+                            metrics::record(&app_handle, metrics);
+                        }
+                        persist_reply(&app_handle, history_id.as_deref(), session, stats);
+                    },
+                )
+                .await
+            }
+            Err(err) => {
+                session.send(OllamaStreamPayload::error(&session.stream_id, err.clone()));
+                Err(err)
+            }
+        };
+        app_handle
+            .state::<StreamRegistry>()
+            .finish(&session.stream_id);
+        let _ = report.send(session.outcome(result));
+    });
+    streams.insert(stream_id, ActiveStream { task: handle, sink });
+    Ok(outcome)
 }
 
 #[tauri::command]
 pub async fn ollama_chat_stream(
     app: AppHandle,
+    webview: Webview,
     request: Value,
     stream_id: String,
-) -> Result<(), String> {
-    let client = build_client()?;
-    let mut payload = request;
-    normalize_image_paths(&mut payload)?;
-    let Some(obj) = payload.as_object_mut() else {
-        return Err("Invalid request payload.".to_string());
+    conversation_id: Option<String>,
+    priority: Option<RequestPriority>,
+    on_event: Option<JavaScriptChannelId>,
+) -> Result<(), ChatError> {
+    let sink = sink::resolve(&app, on_event.map(|id| id.channel_on(webview)));
+    let provider = Provider::resolve(&app)?;
+    let payload = ChatRequest::from_value(request, &config::load_overlay_config(&app).models)?;
+    start_stream(
+        &app,
+        provider,
+        payload,
+        stream_id,
+        conversation_id,
+        priority.unwrap_or_default(),
+        sink,
+    )
+    .await?;
+    Ok(())
+}
+
+// Saves the assembled assistant reply when the caller asked for history.
+fn persist_reply(
+    app: &AppHandle,
+    conversation_id: Option<&str>,
+    session: &StreamSession,
+    stats: Option<&OllamaGenerationStats>,
+) {
+    let Some(conversation_id) = conversation_id else {
+        return;
     };
-    obj.insert("stream".to_string(), Value::Bool(true));
+    let message = HistoryMessage {
+        id: String::new(),
+        role: ChatRole::Assistant,
+        content: session.content.clone(),
+        thinking: (!session.thinking.is_empty()).then(|| session.thinking.clone()),
+        images: Vec::new(),
+        model: Some(session.model.clone()),
+        stats: stats.cloned(),
+        created_at: 0,
+    };
+    if let Err(err) = history::append_message(app, conversation_id, message) {
+        eprintln!("Failed to save streamed reply: {err}");
+    }
+}
 
-    let url = format!("{}/api/chat", OLLAMA_BASE_URL);
-    let response = client
-        .post(url)
-        .json(&payload)
-        .send()
-        .await
-        .map_err(|err| {
-            let detail = describe_reqwest_error(&err);
-            eprintln!("Ollama chat stream request failed: {detail}");
-            detail
-        })?;
-
-    if !response.status().is_success() {
-        let status = response.status();
-        let body = response.text().await.unwrap_or_default();
-        let detail = format!("non-200 from Ollama: {status} {body}");
-        eprintln!("Ollama chat stream request failed: {detail}");
-        return Err(detail);
+#[derive(Debug, Serialize, Clone)]
+struct OllamaPullProgress {
+    pull_id: String,
+    model: String,
+    status: Option<String>,
+    digest: Option<String>,
+    total: Option<u64>,
+    completed: Option<u64>,
+    done: bool,
+    error: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaPullLine {
+    #[serde(default)]
+    status: Option<String>,
+    #[serde(default)]
+    digest: Option<String>,
+    #[serde(default)]
+    total: Option<u64>,
+    #[serde(default)]
+    completed: Option<u64>,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+impl OllamaPullLine {
+    fn failed(detail: String) -> Self {
+        Self {
+            status: None,
+            digest: None,
+            total: None,
+            completed: None,
+            error: Some(detail),
+        }
     }
+}
 
-    let app_handle = app.clone();
-    tauri::async_runtime::spawn(async move {
-        let mut stream = response.bytes_stream();
-        let mut buffer: Vec<u8> = Vec::new();
-
-        while let Some(chunk) = stream.next().await {
-            match chunk {
-                Ok(bytes) => {
-                    buffer.extend_from_slice(&bytes);
-                    // Ollama streams NDJSON chunks separated by newlines.
-                    while let Some(pos) = buffer.iter().position(|byte| *byte == b'\n') {
-                        let line = buffer.drain(..=pos).collect::<Vec<u8>>();
-                        let line = match std::str::from_utf8(&line) {
-                            Ok(line) => line.trim(),
-                            Err(err) => {
-                                eprintln!("Ollama stream decode error: {err}");
-                                continue;
-                            }
-                        };
-                        if line.is_empty() {
-                            continue;
-                        }
-                        let payload = match serde_json::from_str::<Value>(line) {
-                            Ok(payload) => payload,
-                            Err(err) => {
-                                eprintln!("Ollama stream parse error: {err}");
-                                continue;
-                            }
-                        };
-                        let _ = app_handle.emit(
-                            "ollama:chunk",
-                            OllamaStreamPayload {
-                                stream_id: stream_id.clone(),
-                                chunk: Some(payload),
-                                error: None,
-                            },
-                        );
-                    }
-                }
+fn validate_model_name(model: &str) -> Result<&str, String> {
+    let trimmed = model.trim();
+    if trimmed.is_empty() {
+        return Err("Model name is required.".to_string());
+    }
+    Ok(trimmed)
+}
+
+async fn fetch_model_list(app: &AppHandle) -> Result<OllamaModelList, String> {
+    resolve_endpoint(app)?
+        .list_models()
+        .await
+        .inspect_err(|err| eprintln!("Ollama model list failed: {err}"))
+}
+
+async fn fetch_model_info(app: &AppHandle, model: &str) -> Result<OllamaModelInfo, String> {
+    let model = validate_model_name(model)?;
+    resolve_endpoint(app)?.show(model).await
+}
+
+#[tauri::command]
+pub async fn ollama_list_models(app: AppHandle) -> Result<OllamaModelList, String> {
+    fetch_model_list(&app).await
+}
+
+#[tauri::command]
+pub async fn ollama_show_model(app: AppHandle, model: String) -> Result<OllamaModelInfo, String> {
+    fetch_model_info(&app, &model).await
+}
+
+#[tauri::command]
+pub async fn ollama_delete_model(app: AppHandle, model: String) -> Result<(), String> {
+    let model = validate_model_name(&model)?;
+    resolve_endpoint(&app)?.delete_model(model).await
+}
+
+/// Pulls a model, emitting `ollama:pull` progress events until it finishes.
+#[tauri::command]
+pub async fn ollama_pull_model(
+    app: AppHandle,
+    model: String,
+    pull_id: String,
+) -> Result<(), String> {
+    let model = validate_model_name(&model)?.to_string();
+    let response = resolve_endpoint(&app)?.open_pull(&model).await?;
+
+    let emit_progress = |line: OllamaPullLine, done: bool| {
+        let _ = app.emit(
+            "ollama:pull",
+            OllamaPullProgress {
+                pull_id: pull_id.clone(),
+                model: model.clone(),
+                status: line.status,
+                digest: line.digest,
+                total: line.total,
+                completed: line.completed,
+                done,
+                error: line.error,
+            },
+        );
+    };
+
+    let mut stream = response.bytes_stream();
+    let mut buffer: Vec<u8> = Vec::new();
+    loop {
+        let chunk = stream.next().await;
+        let at_end = chunk.is_none();
+        match chunk {
+            Some(Ok(bytes)) => buffer.extend_from_slice(&bytes),
+            Some(Err(err)) => {
+                let detail = describe_reqwest_error(&err, "Ollama");
+                eprintln!("Ollama model pull failed: {detail}");
+                emit_progress(OllamaPullLine::failed(detail.clone()), true);
+                return Err(detail);
+            }
+            // Flush a final line that arrived without a trailing newline.
+            None => buffer.push(b'\n'),
+        }
+        while let Some(line) = next_ndjson_line(&mut buffer) {
+            let Ok(line) = std::str::from_utf8(&line) else {
+                continue;
+            };
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let line = match serde_json::from_str::<OllamaPullLine>(line) {
+                Ok(line) => line,
                 Err(err) => {
-                    let detail = describe_reqwest_error(&err);
-                    eprintln!("Ollama chat stream failed: {detail}");
-                    let _ = app_handle.emit(
-                        "ollama:chunk",
-                        OllamaStreamPayload {
-                            stream_id: stream_id.clone(),
-                            chunk: None,
-                            error: Some(detail),
-                        },
-                    );
-                    break;
+                    eprintln!("Ollama pull parse error: {err}");
+                    continue;
                 }
+            };
+            if let Some(error) = line.error.clone() {
+                let detail = format!("Ollama error: {error}");
+                eprintln!("Ollama model pull failed: {detail}");
+                emit_progress(line, true);
+                return Err(detail);
+            }
+            let done = line.status.as_deref() == Some("success");
+            emit_progress(line, done);
+            if done {
+                return Ok(());
             }
         }
-    });
+        if at_end {
+            break;
+        }
+    }
 
-    Ok(())
+    let detail = "Ollama pull ended before completion.".to_string();
+    emit_progress(OllamaPullLine::failed(detail.clone()), true);
+    Err(detail)
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::Engine;
+    use copilot_client::models::ModelsConfig;
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    // A 1x1 PNG.
+    const TINY_PNG: &str = "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mP8z8BQDwAEhQGAhKmMIQAAAABJRU5ErkJggg==";
+
+    fn request(value: Value) -> ChatRequest {
+        ChatRequest::from_value(value, &ModelsConfig::default()).expect("valid request")
+    }
+
+    #[test]
+    fn normalizes_image_paths_and_uris() {
+        let dir = std::env::temp_dir().join(format!("ollama-tests-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let png = base64::engine::general_purpose::STANDARD
+            .decode(TINY_PNG)
+            .unwrap();
+        let path = dir.join("tiny image.png");
+        std::fs::write(&path, &png).unwrap();
+        let path = path.to_string_lossy().into_owned();
+
+        let mut chat = request(json!({
+            "model": "llava",
+            "messages": [{
+                "role": "user",
+                "content": "What is this?",
+                "images": [
+                    path.clone(),
+                    format!("file://{}", path.replace(' ', "%20")),
+                    format!("data:image/png;base64,{TINY_PNG}"),
+                    TINY_PNG,
+                    "  ",
+                ],
+            }],
+        }));
+        normalize_image_paths(&mut chat).expect("images resolve");
+        let images = &chat.messages[0].images;
+        // The blank entry is dropped rather than sent.
+        assert_eq!(images.len(), 4);
+        for image in images {
+            let bytes = base64::engine::general_purpose::STANDARD
+                .decode(image)
+                .expect("base64 output");
+            assert_eq!(
+                image::guess_format(&bytes).unwrap(),
+                image::ImageFormat::Png
+            );
+        }
+
+        let mut missing = request(json!({
+            "model": "llava",
+            "messages": [{ "role": "user", "content": "?", "images": ["/no/such/file.png"] }],
+        }));
+        let err = normalize_image_paths(&mut missing).unwrap_err();
+        assert!(err.contains("Image file not found"), "{err}");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
 }
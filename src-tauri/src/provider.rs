@@ -0,0 +1,117 @@
+use copilot_client::chat::{ChatError, ChatRequest, ChatResponse};
+use copilot_client::metrics::GenerationMetrics;
+use copilot_client::ollama::OllamaEndpoint;
+use copilot_client::openai::OpenAiEndpoint;
+use copilot_client::provider::{ChatProvider, StreamDecoder};
+use copilot_client::thinking;
+use std::time::Instant;
+use tauri::AppHandle;
+
+use crate::config::{self, ProviderKind};
+use crate::context;
+use crate::metrics;
+use crate::ollama;
+use crate::scheduler::{self, RequestPriority};
+use crate::{secrets, vision, warmup};
+
+pub enum Provider {
+    Ollama(OllamaEndpoint),
+    OpenAi(OpenAiEndpoint),
+}
+
+fn normalize_base_url(raw: &str) -> Result<String, String> {
+    let base_url = raw.trim().trim_end_matches('/').to_string();
+    if !base_url.starts_with("http://") && !base_url.starts_with("https://") {
+        return Err(format!("Invalid provider base URL: '{base_url}'"));
+    }
+    Ok(base_url)
+}
+
+impl Provider {
+    // Resolved per call so profile switches apply without a restart.
+    pub fn resolve(app: &AppHandle) -> Result<Self, String> {
+        let config = config::load_overlay_config(app);
+        let Some(profile) = config.providers.active() else {
+            return Ok(Self::Ollama(ollama::resolve_endpoint(app)?));
+        };
+        let base_url = normalize_base_url(&profile.base_url)?;
+        let api_key = secrets::load_provider_api_key(&profile.id)?;
+        Ok(match profile.kind {
+            ProviderKind::Ollama => Self::Ollama(OllamaEndpoint::new(
+                base_url,
+                api_key.map(|key| format!("Bearer {key}")),
+                profile.verify_tls,
+            )),
+            ProviderKind::OpenaiCompatible => Self::OpenAi(OpenAiEndpoint::new(
+                &profile.name,
+                base_url,
+                api_key,
+                profile.verify_tls,
+            )),
+        })
+    }
+}
+
+impl ChatProvider for Provider {
+    fn service(&self) -> &str {
+        match self {
+            Self::Ollama(provider) => provider.service(),
+            Self::OpenAi(provider) => provider.service(),
+        }
+    }
+
+    async fn health(&self) -> Result<(), String> {
+        match self {
+            Self::Ollama(provider) => provider.health().await,
+            Self::OpenAi(provider) => provider.health().await,
+        }
+    }
+
+    async fn chat(&self, request: &ChatRequest) -> Result<ChatResponse, ChatError> {
+        match self {
+            Self::Ollama(provider) => provider.chat(request).await,
+            Self::OpenAi(provider) => provider.chat(request).await,
+        }
+    }
+
+    async fn open_stream(&self, request: &ChatRequest) -> Result<reqwest::Response, ChatError> {
+        match self {
+            Self::Ollama(provider) => provider.open_stream(request).await,
+            Self::OpenAi(provider) => provider.open_stream(request).await,
+        }
+    }
+
+    fn stream_decoder(&self) -> Box<dyn StreamDecoder> {
+        match self {
+            Self::Ollama(provider) => provider.stream_decoder(),
+            Self::OpenAi(provider) => provider.stream_decoder(),
+        }
+    }
+}
+
+/// Sends a non-streaming chat request through the active provider profile.
+pub async fn send_chat(app: &AppHandle, request: &ChatRequest) -> Result<ChatResponse, ChatError> {
+    send_chat_with_priority(app, request, RequestPriority::Interactive).await
+}
+
+pub async fn send_chat_with_priority(
+    app: &AppHandle,
+    request: &ChatRequest,
+    priority: RequestPriority,
+) -> Result<ChatResponse, ChatError> {
+    let provider = Provider::resolve(app)?;
+    let mut payload = request.clone();
+    ollama::normalize_image_paths(&mut payload)?;
+    vision::route_images(app, &provider, &mut payload, None).await?;
+    let report = context::fit_to_context(app, &provider, &mut payload).await;
+    warmup::remember_model(app, &payload.model);
+    let _permit = scheduler::acquire(app, priority, None, None).await?;
+    let started_at = Instant::now();
+    let mut response = provider.chat(&payload).await?;
+    thinking::split_message(&mut response.message);
+    response.context = report;
+    let metrics =
+        GenerationMetrics::from_stats(&payload.model, &response.stats, None, started_at.elapsed());
+    metrics::record(app, &metrics);
+    Ok(response)
+}
@@ -0,0 +1,291 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::oneshot;
+
+use crate::{config, ollama};
+
+// Queues generation requests so they don't compete for one CPU-bound Ollama.
+// At most `scheduler.max_concurrent` run at once; interactive requests are
+// admitted before background ones, FIFO within a priority.
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum RequestPriority {
+    #[default]
+    Interactive,
+    // Summaries, title generation and other work the user isn't waiting on.
+    Background,
+}
+
+/// Emitted as `ollama:queue`; `position` 0 means the request is now running.
+#[derive(Debug, Serialize, Clone)]
+struct QueueEvent {
+    stream_id: String,
+    position: usize,
+}
+
+struct Slot {
+    // Only streamed requests report queue events or can be superseded.
+    stream_id: Option<String>,
+    conversation_id: Option<String>,
+}
+
+struct Waiter {
+    ticket: u64,
+    priority: RequestPriority,
+    slot: Slot,
+    wake: oneshot::Sender<()>,
+}
+
+#[derive(Default)]
+struct SchedulerState {
+    next_ticket: u64,
+    running: HashMap<u64, Slot>,
+    queue: Vec<Waiter>,
+}
+
+#[derive(Default)]
+pub struct RequestScheduler {
+    state: Mutex<SchedulerState>,
+}
+
+fn emit_position(app: &AppHandle, slot: &Slot, position: usize) {
+    if let Some(stream_id) = &slot.stream_id {
+        let _ = app.emit(
+            "ollama:queue",
+            QueueEvent {
+                stream_id: stream_id.clone(),
+                position,
+            },
+        );
+    }
+}
+
+fn concurrency_limit(app: &AppHandle) -> usize {
+    config::load_overlay_config(app)
+        .scheduler
+        .max_concurrent
+        .max(1) as usize
+}
+
+impl SchedulerState {
+    fn enqueue(&mut self, priority: RequestPriority, slot: Slot) -> (u64, oneshot::Receiver<()>) {
+        let ticket = self.next_ticket;
+        self.next_ticket += 1;
+        let (wake, admitted) = oneshot::channel();
+        self.queue.push(Waiter {
+            ticket,
+            priority,
+            slot,
+            wake,
+        });
+        self.queue
+            .sort_by_key(|waiter| (waiter.priority, waiter.ticket));
+        (ticket, admitted)
+    }
+
+    // Admits waiters while there is capacity and returns everyone's new position.
+    fn admit(&mut self, limit: usize) -> Vec<(&Slot, usize)> {
+        let mut admitted = Vec::new();
+        while self.running.len() < limit && !self.queue.is_empty() {
+            let waiter = self.queue.remove(0);
+            // A closed receiver means the request was cancelled while queued.
+            if waiter.wake.send(()).is_ok() {
+                self.running.insert(waiter.ticket, waiter.slot);
+                admitted.push(waiter.ticket);
+            }
+        }
+        admitted
+            .iter()
+            .filter_map(|ticket| self.running.get(ticket))
+            .map(|slot| (slot, 0))
+            .chain(
+                self.queue
+                    .iter()
+                    .enumerate()
+                    .map(|(index, waiter)| (&waiter.slot, index + 1)),
+            )
+            .collect()
+    }
+
+    // Frees a ticket whether it is still queued or was already admitted;
+    // returns whether it held a place.
+    fn release(&mut self, ticket: u64) -> bool {
+        let before = self.queue.len();
+        self.queue.retain(|waiter| waiter.ticket != ticket);
+        self.running.remove(&ticket).is_some() || self.queue.len() != before
+    }
+}
+
+fn dispatch(app: &AppHandle, state: &mut SchedulerState, limit: usize) {
+    for (slot, position) in state.admit(limit) {
+        emit_position(app, slot, position);
+    }
+}
+
+fn release(app: &AppHandle, ticket: u64) {
+    let limit = concurrency_limit(app);
+    let scheduler = app.state::<RequestScheduler>();
+    let mut state = scheduler
+        .state
+        .lock()
+        .unwrap_or_else(|err| err.into_inner());
+    if state.release(ticket) {
+        dispatch(app, &mut state, limit);
+    }
+}
+
+/// Holds a running slot; dropping it (including by aborting the task) frees it.
+pub struct Permit {
+    app: AppHandle,
+    ticket: u64,
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        release(&self.app, self.ticket);
+    }
+}
+
+// Frees the ticket if the task is aborted before it holds a `Permit`, including
+// after it was admitted but before it was polled again.
+struct QueueGuard<'a> {
+    app: &'a AppHandle,
+    ticket: u64,
+}
+
+impl Drop for QueueGuard<'_> {
+    fn drop(&mut self) {
+        release(self.app, self.ticket);
+    }
+}
+
+/// Waits for a running slot. Cancel-safe: dropping the future leaves the queue.
+pub async fn acquire(
+    app: &AppHandle,
+    priority: RequestPriority,
+    stream_id: Option<String>,
+    conversation_id: Option<String>,
+) -> Result<Permit, String> {
+    let limit = concurrency_limit(app);
+    let (ticket, admitted) = {
+        let scheduler = app.state::<RequestScheduler>();
+        let mut state = scheduler
+            .state
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        let slot = Slot {
+            stream_id,
+            conversation_id,
+        };
+        let queued = state.enqueue(priority, slot);
+        dispatch(app, &mut state, limit);
+        queued
+    };
+    let guard = QueueGuard { app, ticket };
+    admitted
+        .await
+        .map_err(|_| "Request was dropped from the queue.".to_string())?;
+    // The permit owns the slot from here; the guard would free it too early.
+    std::mem::forget(guard);
+    Ok(Permit {
+        app: app.clone(),
+        ticket,
+    })
+}
+
+/// Cancels older streams for the same conversation, queued or running.
+pub fn supersede(app: &AppHandle, conversation_id: &str, stream_id: &str) {
+    let stale = {
+        let scheduler = app.state::<RequestScheduler>();
+        let state = scheduler
+            .state
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        state
+            .running
+            .values()
+            .chain(state.queue.iter().map(|waiter| &waiter.slot))
+            .filter(|slot| slot.conversation_id.as_deref() == Some(conversation_id))
+            .filter_map(|slot| slot.stream_id.clone())
+            .filter(|id| id != stream_id)
+            .collect::<Vec<_>>()
+    };
+    // Aborting the stream task drops its permit or queue entry.
+    for id in stale {
+        ollama::cancel_stream(app, &id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn slot(stream_id: &str) -> Slot {
+        Slot {
+            stream_id: Some(stream_id.to_string()),
+            conversation_id: None,
+        }
+    }
+
+    fn positions(state: &mut SchedulerState, limit: usize) -> Vec<(String, usize)> {
+        state
+            .admit(limit)
+            .into_iter()
+            .map(|(slot, position)| (slot.stream_id.clone().unwrap(), position))
+            .collect()
+    }
+
+    #[test]
+    fn admits_interactive_before_background() {
+        let mut state = SchedulerState::default();
+        let (_, mut first) = state.enqueue(RequestPriority::Interactive, slot("a"));
+        assert_eq!(positions(&mut state, 1), [("a".to_string(), 0)]);
+        let (_, _summary) = state.enqueue(RequestPriority::Background, slot("summary"));
+        let (_, _reply) = state.enqueue(RequestPriority::Interactive, slot("b"));
+        assert_eq!(
+            positions(&mut state, 1),
+            [("b".to_string(), 1), ("summary".to_string(), 2)]
+        );
+        assert!(first.try_recv().is_ok());
+    }
+
+    #[test]
+    fn aborting_after_wake_frees_the_slot() {
+        let mut state = SchedulerState::default();
+        let (running, _running_rx) = state.enqueue(RequestPriority::Interactive, slot("a"));
+        state.admit(1);
+        let (woken, mut woken_rx) = state.enqueue(RequestPriority::Interactive, slot("b"));
+        let (_, mut next_rx) = state.enqueue(RequestPriority::Interactive, slot("c"));
+        state.admit(1);
+
+        // "a" finishes, which wakes "b"...
+        assert!(state.release(running));
+        assert_eq!(
+            positions(&mut state, 1),
+            [("b".to_string(), 0), ("c".to_string(), 1)]
+        );
+        assert!(woken_rx.try_recv().is_ok());
+        // ...but "b" is aborted before it turns the wake-up into a permit.
+        assert!(state.release(woken));
+        assert_eq!(positions(&mut state, 1), [("c".to_string(), 0)]);
+        assert!(next_rx.try_recv().is_ok());
+        assert_eq!(state.running.len(), 1);
+    }
+
+    #[test]
+    fn skips_waiters_cancelled_while_queued() {
+        let mut state = SchedulerState::default();
+        let (_, _running) = state.enqueue(RequestPriority::Interactive, slot("a"));
+        state.admit(1);
+        let (_, cancelled) = state.enqueue(RequestPriority::Interactive, slot("b"));
+        drop(cancelled);
+        let (_, mut next_rx) = state.enqueue(RequestPriority::Interactive, slot("c"));
+        state.admit(2);
+        assert!(next_rx.try_recv().is_ok());
+        assert_eq!(state.running.len(), 2);
+        assert!(state.queue.is_empty());
+    }
+}
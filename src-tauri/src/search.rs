@@ -0,0 +1,65 @@
+use copilot_client::search::{
+    OllamaSearch, OllamaWebSearchResponse, OllamaWebSearchResult, SearchBackend, SearxngSearch,
+};
+use tauri::AppHandle;
+
+use crate::config::{self, WebSearchBackend};
+use crate::secrets;
+
+const OLLAMA_WEB_SEARCH_API_URL: &str = "https://ollama.com/api/web_search";
+const DEFAULT_MAX_RESULTS: u32 = 5;
+
+pub enum WebSearch {
+    Ollama(OllamaSearch),
+    Searxng(SearxngSearch),
+}
+
+impl WebSearch {
+    // Resolved per call so backend changes in Preferences apply immediately.
+    pub fn resolve(app: &AppHandle) -> Result<Self, String> {
+        let tools = config::load_overlay_config(app).tools;
+        match tools.web_search_backend {
+            WebSearchBackend::Ollama => Ok(Self::Ollama(OllamaSearch::new(
+                OLLAMA_WEB_SEARCH_API_URL,
+                secrets::load_web_search_api_key()?,
+            ))),
+            WebSearchBackend::Searxng => {
+                let backend = SearxngSearch::new(&tools.searxng_url);
+                if !backend.base_url().starts_with("http://")
+                    && !backend.base_url().starts_with("https://")
+                {
+                    return Err("Set a SearxNG URL in Preferences to use web search.".into());
+                }
+                Ok(Self::Searxng(backend))
+            }
+        }
+    }
+}
+
+impl SearchBackend for WebSearch {
+    async fn search(
+        &self,
+        query: &str,
+        max_results: u32,
+    ) -> Result<Vec<OllamaWebSearchResult>, String> {
+        match self {
+            Self::Ollama(backend) => backend.search(query, max_results).await,
+            Self::Searxng(backend) => backend.search(query, max_results).await,
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn ollama_web_search(
+    app: AppHandle,
+    query: String,
+    max_results: Option<u32>,
+) -> Result<OllamaWebSearchResponse, String> {
+    let backend = WebSearch::resolve(&app)?;
+    let max_results = max_results.unwrap_or(DEFAULT_MAX_RESULTS).max(1);
+    let results = backend
+        .search(&query, max_results)
+        .await
+        .inspect_err(|err| eprintln!("Web search failed: {err}"))?;
+    Ok(OllamaWebSearchResponse { results })
+}
@@ -3,6 +3,8 @@ use serde::Serialize;
 const KEYRING_SERVICE: &str = "desktop-copilot";
 const OLLAMA_WEB_SEARCH_API_KEY_ENV: &str = "OLLAMA_WEB_SEARCH_API_KEY";
 const WEB_SEARCH_KEY_NAME: &str = "ollama_web_search_api_key";
+const OLLAMA_AUTH_HEADER_ENV: &str = "OLLAMA_AUTH_HEADER";
+const ENDPOINT_AUTH_KEY_NAME: &str = "ollama_endpoint_auth_header";
 
 #[derive(Debug, Serialize, Clone)]
 pub struct WebSearchKeyStatus {
@@ -10,20 +12,19 @@ pub struct WebSearchKeyStatus {
     pub source: Option<String>,
 }
 
-fn keyring_entry() -> Result<keyring::Entry, String> {
-    keyring::Entry::new(KEYRING_SERVICE, WEB_SEARCH_KEY_NAME)
-        .map_err(|err| format!("Keyring init error: {err}"))
+fn keyring_entry(name: &str) -> Result<keyring::Entry, String> {
+    keyring::Entry::new(KEYRING_SERVICE, name).map_err(|err| format!("Keyring init error: {err}"))
 }
 
-fn env_key() -> Option<String> {
-    std::env::var(OLLAMA_WEB_SEARCH_API_KEY_ENV)
+fn env_key(var: &str) -> Option<String> {
+    std::env::var(var)
         .ok()
         .map(|value| value.trim().to_string())
         .filter(|value| !value.is_empty())
 }
 
-fn keyring_key() -> Result<Option<String>, String> {
-    let entry = keyring_entry()?;
+fn keyring_key(name: &str) -> Result<Option<String>, String> {
+    let entry = keyring_entry(name)?;
     match entry.get_password() {
         Ok(value) => {
             let trimmed = value.trim().to_string();
@@ -38,25 +39,14 @@ fn keyring_key() -> Result<Option<String>, String> {
     }
 }
 
-pub fn load_web_search_api_key() -> Result<String, String> {
-    if let Some(key) = env_key() {
-        return Ok(key);
-    }
-    if let Some(key) = keyring_key()? {
-        return Ok(key);
-    }
-    Err("Missing OLLAMA_WEB_SEARCH_API_KEY env var or saved key.".to_string())
-}
-
-#[tauri::command]
-pub fn get_ollama_web_search_key_status() -> Result<WebSearchKeyStatus, String> {
-    if env_key().is_some() {
+fn key_status(var: &str, name: &str) -> Result<WebSearchKeyStatus, String> {
+    if env_key(var).is_some() {
         return Ok(WebSearchKeyStatus {
             has_key: true,
             source: Some("env".to_string()),
         });
     }
-    if keyring_key()?.is_some() {
+    if keyring_key(name)?.is_some() {
         return Ok(WebSearchKeyStatus {
             has_key: true,
             source: Some("keyring".to_string()),
@@ -68,24 +58,125 @@ pub fn get_ollama_web_search_key_status() -> Result<WebSearchKeyStatus, String>
     })
 }
 
+fn store_key(name: &str, value: &str) -> Result<(), String> {
+    let entry = keyring_entry(name)?;
+    entry
+        .set_password(value)
+        .map_err(|err| format!("Keyring write error: {err}"))
+}
+
+fn delete_key(name: &str) -> Result<(), String> {
+    let entry = keyring_entry(name)?;
+    match entry.delete_password() {
+        Ok(()) => Ok(()),
+        Err(keyring::Error::NoEntry) => Ok(()),
+        Err(err) => Err(format!("Keyring delete error: {err}")),
+    }
+}
+
+pub fn load_web_search_api_key() -> Result<String, String> {
+    if let Some(key) = env_key(OLLAMA_WEB_SEARCH_API_KEY_ENV) {
+        return Ok(key);
+    }
+    if let Some(key) = keyring_key(WEB_SEARCH_KEY_NAME)? {
+        return Ok(key);
+    }
+    Err("Missing OLLAMA_WEB_SEARCH_API_KEY env var or saved key.".to_string())
+}
+
+/// Full `Authorization` header value for the Ollama endpoint (e.g. `Basic ...`), if any.
+pub fn load_endpoint_auth_header() -> Result<Option<String>, String> {
+    if let Some(value) = env_key(OLLAMA_AUTH_HEADER_ENV) {
+        return Ok(Some(value));
+    }
+    keyring_key(ENDPOINT_AUTH_KEY_NAME)
+}
+
+fn provider_key_name(profile_id: &str) -> Result<String, String> {
+    let trimmed = profile_id.trim();
+    if trimmed.is_empty() {
+        return Err("Provider profile id is required.".to_string());
+    }
+    Ok(format!("provider_api_key_{trimmed}"))
+}
+
+/// API key for a custom provider profile, sent as a bearer token.
+pub fn load_provider_api_key(profile_id: &str) -> Result<Option<String>, String> {
+    keyring_key(&provider_key_name(profile_id)?)
+}
+
+#[tauri::command]
+pub fn get_ollama_web_search_key_status() -> Result<WebSearchKeyStatus, String> {
+    key_status(OLLAMA_WEB_SEARCH_API_KEY_ENV, WEB_SEARCH_KEY_NAME)
+}
+
 #[tauri::command]
 pub fn set_ollama_web_search_api_key(key: String) -> Result<(), String> {
     let trimmed = key.trim();
     if trimmed.is_empty() {
         return Err("API key is required.".to_string());
     }
-    let entry = keyring_entry()?;
-    entry
-        .set_password(trimmed)
-        .map_err(|err| format!("Keyring write error: {err}"))
+    store_key(WEB_SEARCH_KEY_NAME, trimmed)
 }
 
 #[tauri::command]
 pub fn clear_ollama_web_search_api_key() -> Result<(), String> {
-    let entry = keyring_entry()?;
-    match entry.delete_password() {
-        Ok(()) => Ok(()),
-        Err(keyring::Error::NoEntry) => Ok(()),
-        Err(err) => Err(format!("Keyring delete error: {err}")),
+    delete_key(WEB_SEARCH_KEY_NAME)
+}
+
+#[tauri::command]
+pub fn get_ollama_endpoint_auth_status() -> Result<WebSearchKeyStatus, String> {
+    key_status(OLLAMA_AUTH_HEADER_ENV, ENDPOINT_AUTH_KEY_NAME)
+}
+
+#[tauri::command]
+pub fn set_ollama_endpoint_auth_header(
+    app: tauri::AppHandle,
+    header: String,
+) -> Result<(), String> {
+    let trimmed = header.trim();
+    if trimmed.is_empty() {
+        return Err("Authorization header is required.".to_string());
     }
+    store_key(ENDPOINT_AUTH_KEY_NAME, trimmed)?;
+    crate::config::emit_config_updated(&app);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn clear_ollama_endpoint_auth_header(app: tauri::AppHandle) -> Result<(), String> {
+    delete_key(ENDPOINT_AUTH_KEY_NAME)?;
+    crate::config::emit_config_updated(&app);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_provider_api_key_status(profile_id: String) -> Result<WebSearchKeyStatus, String> {
+    let has_key = load_provider_api_key(&profile_id)?.is_some();
+    Ok(WebSearchKeyStatus {
+        has_key,
+        source: has_key.then(|| "keyring".to_string()),
+    })
+}
+
+#[tauri::command]
+pub fn set_provider_api_key(
+    app: tauri::AppHandle,
+    profile_id: String,
+    key: String,
+) -> Result<(), String> {
+    let trimmed = key.trim();
+    if trimmed.is_empty() {
+        return Err("API key is required.".to_string());
+    }
+    store_key(&provider_key_name(&profile_id)?, trimmed)?;
+    crate::config::emit_config_updated(&app);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn clear_provider_api_key(app: tauri::AppHandle, profile_id: String) -> Result<(), String> {
+    delete_key(&provider_key_name(&profile_id)?)?;
+    crate::config::emit_config_updated(&app);
+    Ok(())
 }
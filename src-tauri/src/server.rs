@@ -0,0 +1,508 @@
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Read};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::config;
+
+// Optional supervisor for a local `ollama serve` child process. It restarts the
+// server on crash (with backoff) and stops it when the app exits.
+
+const MAX_LOG_LINES: usize = 500;
+const MAX_RESTARTS: u32 = 5;
+// A run longer than this counts as healthy and resets the restart budget.
+const STABLE_RUN: Duration = Duration::from_secs(60);
+const WATCH_INTERVAL: Duration = Duration::from_secs(1);
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(5);
+// Ollama's port when `OLLAMA_HOST` doesn't name one.
+const DEFAULT_PORT: u16 = 11434;
+const PROBE_TIMEOUT: Duration = Duration::from_millis(300);
+
+#[derive(Default)]
+struct SupervisorInner {
+    child: Option<Child>,
+    // Whether the user wants the server running; cleared by `stop`.
+    desired: bool,
+    started_at: Option<Instant>,
+    restarts: u32,
+    // Bumped per `start` so a stale watcher thread exits.
+    generation: u64,
+    last_error: Option<String>,
+    logs: VecDeque<String>,
+}
+
+#[derive(Default)]
+pub struct OllamaSupervisor {
+    inner: Mutex<SupervisorInner>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OllamaServerStatus {
+    pub managed: bool,
+    pub running: bool,
+    pub pid: Option<u32>,
+    pub host: String,
+    pub restarts: u32,
+    pub error: Option<String>,
+}
+
+fn find_ollama_binary() -> Option<PathBuf> {
+    let names: &[&str] = if cfg!(windows) {
+        &["ollama.exe"]
+    } else {
+        &["ollama"]
+    };
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path)
+        .flat_map(|dir| names.iter().map(move |name| dir.join(name)))
+        .find(|candidate| candidate.is_file())
+}
+
+fn push_log(app: &AppHandle, line: String) {
+    let supervisor = app.state::<OllamaSupervisor>();
+    let mut inner = supervisor
+        .inner
+        .lock()
+        .unwrap_or_else(|err| err.into_inner());
+    if inner.logs.len() >= MAX_LOG_LINES {
+        inner.logs.pop_front();
+    }
+    inner.logs.push_back(line);
+}
+
+fn capture_output<R: Read + Send + 'static>(app: &AppHandle, reader: R) {
+    let app = app.clone();
+    std::thread::spawn(move || {
+        for line in BufReader::new(reader).lines().map_while(Result::ok) {
+            push_log(&app, line);
+        }
+    });
+}
+
+fn server_command(binary: &Path, host: &str) -> Command {
+    let mut command = Command::new(binary);
+    command
+        .arg("serve")
+        .env("OLLAMA_HOST", host)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        // Give the child its own hidden console so `request_exit` can signal it.
+        const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+        command.creation_flags(CREATE_NO_WINDOW);
+    }
+    command
+}
+
+/// Where a server started with `OLLAMA_HOST=host` can be reached, following
+/// ollama's own parsing: optional scheme, default port, wildcard binds.
+fn probe_target(host: &str) -> (String, u16) {
+    let host = host.trim();
+    let host = host.split_once("://").map_or(host, |(_, rest)| rest);
+    let host = host.split('/').next().unwrap_or_default();
+    let (name, port) = host
+        .rsplit_once(':')
+        .and_then(|(name, port)| Some((name, port.parse().ok()?)))
+        .unwrap_or((host, DEFAULT_PORT));
+    let name = match name.trim_start_matches('[').trim_end_matches(']') {
+        "" | "0.0.0.0" => "127.0.0.1",
+        "::" => "::1",
+        name => name,
+    };
+    (name.to_string(), port)
+}
+
+// A second `ollama serve` on a busy port exits at once, which would burn
+// through the restart budget; an existing server is reported instead.
+fn ensure_port_free(host: &str) -> Result<(), String> {
+    let (name, port) = probe_target(host);
+    let listening = (name.as_str(), port)
+        .to_socket_addrs()
+        .map(|mut addrs| addrs.any(|addr| TcpStream::connect_timeout(&addr, PROBE_TIMEOUT).is_ok()))
+        .unwrap_or(false);
+    if listening {
+        return Err(format!(
+            "Ollama is already running on {host}; stop it or change local_server.host."
+        ));
+    }
+    Ok(())
+}
+
+fn spawn_server(app: &AppHandle, host: &str) -> Result<Child, String> {
+    let binary = find_ollama_binary().ok_or("Could not find `ollama` on PATH.")?;
+    let mut command = server_command(&binary, host);
+    let mut child = command
+        .spawn()
+        .map_err(|err| format!("Failed to start {}: {err}", binary.display()))?;
+    if let Some(stdout) = child.stdout.take() {
+        capture_output(app, stdout);
+    }
+    if let Some(stderr) = child.stderr.take() {
+        capture_output(app, stderr);
+    }
+    println!("Started ollama serve (pid {}) on {host}", child.id());
+    Ok(child)
+}
+
+fn status_of(inner: &SupervisorInner, host: String) -> OllamaServerStatus {
+    OllamaServerStatus {
+        managed: inner.desired,
+        running: inner.child.is_some(),
+        pid: inner.child.as_ref().map(Child::id),
+        host,
+        restarts: inner.restarts,
+        error: inner.last_error.clone(),
+    }
+}
+
+fn emit_status(app: &AppHandle, inner: &SupervisorInner) {
+    let host = config::load_overlay_config(app).local_server.host;
+    let _ = app.emit("ollama:server", status_of(inner, host));
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum RestartPlan {
+    Retry { attempt: u32, backoff: Duration },
+    GiveUp,
+}
+
+/// Decides what to do after the child exits, given the restarts so far and
+/// how long the last run lasted.
+fn plan_restart(restarts: u32, ran_for: Option<Duration>) -> RestartPlan {
+    let restarts = if ran_for.is_some_and(|ran| ran >= STABLE_RUN) {
+        0
+    } else {
+        restarts
+    };
+    if restarts >= MAX_RESTARTS {
+        return RestartPlan::GiveUp;
+    }
+    let attempt = restarts + 1;
+    RestartPlan::Retry {
+        attempt,
+        backoff: Duration::from_secs(1 << attempt.min(5)),
+    }
+}
+
+fn watch(app: AppHandle, generation: u64) {
+    loop {
+        std::thread::sleep(WATCH_INTERVAL);
+        let supervisor = app.state::<OllamaSupervisor>();
+        let mut inner = supervisor
+            .inner
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        if !inner.desired || inner.generation != generation {
+            return;
+        }
+        let exited = match inner.child.as_mut().map(Child::try_wait) {
+            Some(Ok(Some(status))) => Some(format!("ollama serve exited ({status})")),
+            Some(Err(err)) => Some(format!("Failed to poll ollama serve: {err}")),
+            Some(Ok(None)) => None,
+            // A previous restart attempt failed to spawn.
+            None => Some("ollama serve is not running".to_string()),
+        };
+        let Some(reason) = exited else {
+            continue;
+        };
+        eprintln!("{reason}");
+        inner.child = None;
+        let ran_for = inner.started_at.map(|started| started.elapsed());
+        let RestartPlan::Retry { attempt, backoff } = plan_restart(inner.restarts, ran_for) else {
+            inner.desired = false;
+            inner.last_error = Some(format!(
+                "{reason}; giving up after {MAX_RESTARTS} restarts."
+            ));
+            emit_status(&app, &inner);
+            return;
+        };
+        inner.restarts = attempt;
+        inner.last_error = Some(reason);
+        emit_status(&app, &inner);
+
+        // Back off without holding the lock so `stop` isn't blocked.
+        drop(inner);
+        std::thread::sleep(backoff);
+
+        let mut inner = supervisor
+            .inner
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        if !inner.desired || inner.generation != generation {
+            return;
+        }
+        let host = config::load_overlay_config(&app).local_server.host;
+        if let Err(err) = ensure_port_free(&host) {
+            eprintln!("{err}");
+            inner.desired = false;
+            inner.last_error = Some(err);
+            emit_status(&app, &inner);
+            return;
+        }
+        match spawn_server(&app, &host) {
+            Ok(child) => {
+                inner.child = Some(child);
+                inner.started_at = Some(Instant::now());
+            }
+            Err(err) => {
+                eprintln!("Ollama restart failed: {err}");
+                inner.last_error = Some(err);
+            }
+        }
+        emit_status(&app, &inner);
+    }
+}
+
+#[cfg(unix)]
+fn request_exit(child: &Child) {
+    // SIGTERM lets ollama unload models and release the port cleanly.
+    // SAFETY: `kill` has no memory-safety preconditions. The pid belongs to a
+    // child we haven't reaped yet, so it can't have been reused by another process.
+    unsafe {
+        libc::kill(child.id() as libc::pid_t, libc::SIGTERM);
+    }
+}
+
+#[cfg(windows)]
+fn request_exit(child: &Child) {
+    use windows::Win32::System::Console::{
+        AttachConsole, FreeConsole, GenerateConsoleCtrlEvent, GetConsoleWindow,
+        SetConsoleCtrlHandler, ATTACH_PARENT_PROCESS, CTRL_C_EVENT,
+    };
+    // Windows has no SIGTERM; Ctrl+C on the child's console is what ollama
+    // handles as a graceful shutdown. We join that console just long enough to
+    // send it, ignoring the event ourselves until `stop_child` is done waiting.
+    // SAFETY: these calls take no pointers. Release builds have no console; a
+    // debug build started from a terminal is detached from it only while the
+    // event is sent and then re-attached to the parent's console.
+    unsafe {
+        let had_console = !GetConsoleWindow().is_invalid();
+        if had_console {
+            let _ = FreeConsole();
+        }
+        if AttachConsole(child.id()).is_ok() {
+            let _ = SetConsoleCtrlHandler(None, true);
+            let _ = GenerateConsoleCtrlEvent(CTRL_C_EVENT, 0);
+            let _ = FreeConsole();
+        }
+        if had_console {
+            let _ = AttachConsole(ATTACH_PARENT_PROCESS);
+        }
+    }
+}
+
+// Undoes the Ctrl+C mask set by `request_exit`. The event is delivered
+// asynchronously, so this waits until the child is gone.
+#[cfg(windows)]
+fn restore_ctrl_c() {
+    use windows::Win32::System::Console::SetConsoleCtrlHandler;
+    // SAFETY: no pointers; removes the "ignore Ctrl+C" flag for this process.
+    unsafe {
+        let _ = SetConsoleCtrlHandler(None, false);
+    }
+}
+
+#[cfg(not(windows))]
+fn restore_ctrl_c() {}
+
+#[cfg(not(any(unix, windows)))]
+fn request_exit(_child: &Child) {}
+
+fn stop_child(mut child: Child) {
+    request_exit(&child);
+    let deadline = Instant::now() + SHUTDOWN_GRACE;
+    let mut exited = false;
+    while !exited && Instant::now() < deadline {
+        exited = matches!(child.try_wait(), Ok(Some(_)));
+        if !exited {
+            std::thread::sleep(Duration::from_millis(100));
+        }
+    }
+    if !exited {
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+    restore_ctrl_c();
+}
+
+/// Launches `ollama serve` if it isn't already running under the supervisor.
+pub fn start(app: &AppHandle) -> Result<OllamaServerStatus, String> {
+    let host = config::load_overlay_config(app).local_server.host;
+    let supervisor = app.state::<OllamaSupervisor>();
+    let mut inner = supervisor
+        .inner
+        .lock()
+        .unwrap_or_else(|err| err.into_inner());
+    if inner.child.is_none() {
+        let child = ensure_port_free(&host)
+            .and_then(|()| spawn_server(app, &host))
+            .inspect_err(|err| {
+                eprintln!("Ollama server start failed: {err}");
+                inner.last_error = Some(err.clone());
+            })?;
+        inner.child = Some(child);
+        inner.started_at = Some(Instant::now());
+        inner.restarts = 0;
+        inner.last_error = None;
+    }
+    if !inner.desired {
+        inner.desired = true;
+        inner.generation += 1;
+        let generation = inner.generation;
+        let app = app.clone();
+        std::thread::spawn(move || watch(app, generation));
+    }
+    emit_status(app, &inner);
+    Ok(status_of(&inner, host))
+}
+
+/// Stops the managed server, if any. Safe to call when nothing is running.
+pub fn shutdown(app: &AppHandle) {
+    let supervisor = app.state::<OllamaSupervisor>();
+    let child = {
+        let mut inner = supervisor
+            .inner
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        inner.desired = false;
+        inner.child.take()
+    };
+    if let Some(child) = child {
+        println!("Stopping ollama serve (pid {})", child.id());
+        stop_child(child);
+    }
+}
+
+/// Starts or stops the server after `local_server.managed` is toggled.
+pub fn apply_config(app: &AppHandle, managed: bool) {
+    let desired = app
+        .state::<OllamaSupervisor>()
+        .inner
+        .lock()
+        .unwrap_or_else(|err| err.into_inner())
+        .desired;
+    if managed && !desired {
+        // Failures are recorded in the supervisor status.
+        let _ = start(app);
+    } else if !managed && desired {
+        // Stopping waits for the child, so keep it off the command thread.
+        let app = app.clone();
+        std::thread::spawn(move || {
+            shutdown(&app);
+            let supervisor = app.state::<OllamaSupervisor>();
+            let inner = supervisor
+                .inner
+                .lock()
+                .unwrap_or_else(|err| err.into_inner());
+            emit_status(&app, &inner);
+        });
+    }
+}
+
+#[tauri::command]
+pub fn start_ollama_server(app: AppHandle) -> Result<OllamaServerStatus, String> {
+    start(&app)
+}
+
+#[tauri::command]
+pub fn stop_ollama_server(app: AppHandle, state: State<OllamaSupervisor>) -> OllamaServerStatus {
+    shutdown(&app);
+    let inner = state.inner.lock().unwrap_or_else(|err| err.into_inner());
+    emit_status(&app, &inner);
+    status_of(&inner, config::load_overlay_config(&app).local_server.host)
+}
+
+#[tauri::command]
+pub fn ollama_server_status(app: AppHandle, state: State<OllamaSupervisor>) -> OllamaServerStatus {
+    let inner = state.inner.lock().unwrap_or_else(|err| err.into_inner());
+    status_of(&inner, config::load_overlay_config(&app).local_server.host)
+}
+
+#[tauri::command]
+pub fn ollama_server_logs(state: State<OllamaSupervisor>, limit: Option<usize>) -> Vec<String> {
+    let inner = state.inner.lock().unwrap_or_else(|err| err.into_inner());
+    let limit = limit.unwrap_or(MAX_LOG_LINES);
+    let skip = inner.logs.len().saturating_sub(limit);
+    inner.logs.iter().skip(skip).cloned().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::OsStr;
+
+    #[test]
+    fn backs_off_exponentially_then_gives_up() {
+        let quick = Some(Duration::from_secs(2));
+        let backoffs = (0..MAX_RESTARTS)
+            .map(|restarts| match plan_restart(restarts, quick) {
+                RestartPlan::Retry { attempt, backoff } => {
+                    assert_eq!(attempt, restarts + 1);
+                    backoff.as_secs()
+                }
+                RestartPlan::GiveUp => panic!("gave up after {restarts} restarts"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(backoffs, [2, 4, 8, 16, 32]);
+        assert_eq!(plan_restart(MAX_RESTARTS, quick), RestartPlan::GiveUp);
+    }
+
+    #[test]
+    fn stable_run_resets_the_restart_budget() {
+        assert_eq!(
+            plan_restart(MAX_RESTARTS, Some(STABLE_RUN)),
+            RestartPlan::Retry {
+                attempt: 1,
+                backoff: Duration::from_secs(2),
+            }
+        );
+        // A spawn that never started has no run time to earn a reset.
+        assert_eq!(plan_restart(MAX_RESTARTS, None), RestartPlan::GiveUp);
+    }
+
+    #[test]
+    fn command_serves_on_the_configured_host() {
+        let command = server_command(Path::new("/opt/ollama/bin/ollama"), "0.0.0.0:11500");
+        assert_eq!(command.get_program(), OsStr::new("/opt/ollama/bin/ollama"));
+        assert_eq!(
+            command.get_args().collect::<Vec<_>>(),
+            [OsStr::new("serve")]
+        );
+        assert_eq!(
+            command.get_envs().collect::<Vec<_>>(),
+            [(OsStr::new("OLLAMA_HOST"), Some(OsStr::new("0.0.0.0:11500")))]
+        );
+    }
+
+    #[test]
+    fn probes_where_ollama_would_listen() {
+        let target = |host: &str| {
+            let (name, port) = probe_target(host);
+            format!("{name} {port}")
+        };
+        assert_eq!(target("127.0.0.1:11434"), "127.0.0.1 11434");
+        assert_eq!(target("0.0.0.0"), "127.0.0.1 11434");
+        assert_eq!(target(":11500"), "127.0.0.1 11500");
+        assert_eq!(target("http://localhost:8080/"), "localhost 8080");
+        assert_eq!(target("[::]:11434"), "::1 11434");
+        assert_eq!(target("[::1]"), "::1 11434");
+    }
+
+    #[test]
+    fn reports_a_server_already_on_the_port() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let host = listener.local_addr().unwrap().to_string();
+        let err = ensure_port_free(&host).unwrap_err();
+        assert!(err.contains("already running"), "{err}");
+        drop(listener);
+        assert_eq!(ensure_port_free(&host), Ok(()));
+    }
+}
@@ -6,6 +6,7 @@ use tauri_plugin_global_shortcut::{GlobalShortcutExt, ShortcutState};
 
 use crate::{
     config::OverlayConfig,
+    ollama,
     overlay::{snap_overlay_to_corner, toggle_overlay_window, OverlayState},
 };
 
@@ -55,6 +56,9 @@ pub fn register_overlay_shortcut(app: &tauri::AppHandle, config: &OverlayConfig)
         if let Some(key) = normalized_keybind(previous.focus_overlay.as_str()) {
             let _ = app.global_shortcut().unregister(key);
         }
+        if let Some(key) = normalized_keybind(previous.stop_generation.as_str()) {
+            let _ = app.global_shortcut().unregister(key);
+        }
     }
 
     // Debounce state to avoid repeat key events.
@@ -64,6 +68,7 @@ pub fn register_overlay_shortcut(app: &tauri::AppHandle, config: &OverlayConfig)
 
     let toggle_keybind = config.keybinds.toggle_overlay.clone();
     let focus_keybind = config.keybinds.focus_overlay.clone();
+    let stop_keybind = config.keybinds.stop_generation.clone();
 
     if let Some(key) = normalized_keybind(toggle_keybind.as_str()) {
         if let Err(error) = app
@@ -127,5 +132,23 @@ pub fn register_overlay_shortcut(app: &tauri::AppHandle, config: &OverlayConfig)
         }
     }
 
+    if let Some(key) = normalized_keybind(stop_keybind.as_str()) {
+        if let Err(error) = app
+            .global_shortcut()
+            .on_shortcut(key, move |app, _, event| {
+                if event.state != ShortcutState::Pressed {
+                    return;
+                }
+                // Abort in the backend so the model stops generating, not just rendering.
+                ollama::cancel_active_streams(app);
+            })
+        {
+            eprintln!("Failed to register stop generation shortcut: {error}");
+            emit_shortcut_error(app, key, &error.to_string());
+        } else {
+            println!("{} hotkey registered", key);
+        }
+    }
+
     store_last_keybinds(config);
 }
@@ -0,0 +1,35 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use copilot_client::stream::{OllamaStreamPayload, StreamSink};
+use tauri::ipc::Channel;
+use tauri::{AppHandle, Emitter};
+
+use crate::config;
+
+// Delivery of stream events. Each call passes its own IPC channel so chunks
+// only reach the caller; callers without one fall back to the global
+// `ollama:chunk` event, which `streaming.legacy_events` opts into explicitly.
+
+static WARNED_LEGACY: AtomicBool = AtomicBool::new(false);
+
+pub fn resolve(app: &AppHandle, channel: Option<Channel<OllamaStreamPayload>>) -> StreamSink {
+    // A closed channel means the window went away; the stream still finishes
+    // so history and metrics are recorded.
+    if let Some(channel) = channel {
+        return StreamSink::new(move |payload| {
+            let _ = channel.send(payload);
+        });
+    }
+    if !config::load_overlay_config(app).streaming.legacy_events
+        && !WARNED_LEGACY.swap(true, Ordering::Relaxed)
+    {
+        eprintln!(
+            "Stream started without an onEvent channel; falling back to the \
+             ollama:chunk event. Pass onEvent or enable streaming.legacy_events."
+        );
+    }
+    let app = app.clone();
+    StreamSink::new(move |payload| {
+        let _ = app.emit("ollama:chunk", payload);
+    })
+}
@@ -0,0 +1,576 @@
+use copilot_client::chat::{
+    ChatError, ChatMessage, ChatRequest, ChatResponse, ChatRole, ValidationIssue,
+};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::future::Future;
+use tauri::AppHandle;
+
+use crate::{config, provider};
+
+// Structured output: the schema goes to Ollama as `format`, and the reply is
+// checked here too because models don't always honour it. Mismatches are fed
+// back to the model and retried.
+//
+// The validator covers the JSON Schema subset models are asked to produce:
+// type, enum, const, properties/required/additionalProperties, items,
+// length/size/range bounds, allOf/anyOf/oneOf, and local `$ref`s. Schemas
+// using anything else (`pattern`, `format`, `if`/`then`, ...) are rejected up
+// front rather than half-checked.
+
+const DEFAULT_ATTEMPTS: u32 = 3;
+const MAX_ATTEMPTS: u32 = 5;
+// `$ref` chains deeper than this are treated as a broken schema.
+const MAX_DEPTH: usize = 32;
+const CHECKED_KEYWORDS: &[&str] = &[
+    "type",
+    "enum",
+    "const",
+    "properties",
+    "required",
+    "additionalProperties",
+    "items",
+    "minItems",
+    "maxItems",
+    "minLength",
+    "maxLength",
+    "minimum",
+    "maximum",
+    "exclusiveMinimum",
+    "exclusiveMaximum",
+    "allOf",
+    "anyOf",
+    "oneOf",
+    "$ref",
+];
+// Annotations and containers that don't constrain the value themselves.
+const IGNORED_KEYWORDS: &[&str] = &[
+    "title",
+    "description",
+    "default",
+    "examples",
+    "deprecated",
+    "readOnly",
+    "writeOnly",
+    "$schema",
+    "$id",
+    "$comment",
+    "$defs",
+    "definitions",
+];
+
+#[derive(Debug, Serialize, Clone)]
+pub struct StructuredChatResponse {
+    pub data: Value,
+    pub attempts: u32,
+    pub response: ChatResponse,
+}
+
+struct Validator<'a> {
+    root: &'a Value,
+    issues: Vec<ValidationIssue>,
+}
+
+fn type_matches(expected: &str, value: &Value) -> bool {
+    match expected {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        "number" => value.is_number(),
+        "integer" => {
+            value.is_i64() || value.is_u64() || value.as_f64().is_some_and(|n| n.fract() == 0.0)
+        }
+        _ => true,
+    }
+}
+
+impl<'a> Validator<'a> {
+    fn issue(&mut self, path: &str, message: impl Into<String>) {
+        self.issues.push(ValidationIssue {
+            field: path.to_string(),
+            message: message.into(),
+        });
+    }
+
+    fn resolve(&self, reference: &str) -> Option<&'a Value> {
+        let pointer = reference.strip_prefix('#')?;
+        self.root.pointer(pointer)
+    }
+
+    // Runs a sub-validation and reports whether it produced no issues.
+    fn passes(&self, schema: &'a Value, value: &Value, path: &str, depth: usize) -> bool {
+        let mut nested = Validator {
+            root: self.root,
+            issues: Vec::new(),
+        };
+        nested.check(schema, value, path, depth);
+        nested.issues.is_empty()
+    }
+
+    fn check(&mut self, schema: &'a Value, value: &Value, path: &str, depth: usize) {
+        if depth > MAX_DEPTH {
+            self.issue(path, "schema nesting is too deep");
+            return;
+        }
+        let Some(schema) = schema.as_object() else {
+            // `true` accepts anything and `false` rejects everything.
+            if schema == &Value::Bool(false) {
+                self.issue(path, "no value is allowed here");
+            }
+            return;
+        };
+        if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+            match self.resolve(reference) {
+                Some(target) => self.check(target, value, path, depth + 1),
+                None => self.issue(path, format!("unresolvable $ref '{reference}'")),
+            }
+        }
+
+        match schema.get("type") {
+            Some(Value::String(expected)) if !type_matches(expected, value) => {
+                self.issue(path, format!("expected {expected}"));
+                return;
+            }
+            Some(Value::Array(options)) => {
+                let allowed = options.iter().filter_map(Value::as_str).collect::<Vec<_>>();
+                if !allowed.iter().any(|expected| type_matches(expected, value)) {
+                    self.issue(path, format!("expected one of {}", allowed.join(", ")));
+                    return;
+                }
+            }
+            _ => {}
+        }
+        if let Some(options) = schema.get("enum").and_then(Value::as_array) {
+            if !options.contains(value) {
+                self.issue(
+                    path,
+                    format!("must be one of {}", Value::Array(options.clone())),
+                );
+            }
+        }
+        if let Some(expected) = schema.get("const") {
+            if expected != value {
+                self.issue(path, format!("must equal {expected}"));
+            }
+        }
+
+        match value {
+            Value::Object(object) => self.check_object(schema, object, path, depth),
+            Value::Array(items) => self.check_array(schema, items, path, depth),
+            Value::String(text) => self.check_string(schema, text, path),
+            Value::Number(number) => {
+                if let Some(number) = number.as_f64() {
+                    self.check_number(schema, number, path);
+                }
+            }
+            _ => {}
+        }
+
+        if let Some(all) = schema.get("allOf").and_then(Value::as_array) {
+            for sub in all {
+                self.check(sub, value, path, depth + 1);
+            }
+        }
+        if let Some(any) = schema.get("anyOf").and_then(Value::as_array) {
+            if !any
+                .iter()
+                .any(|sub| self.passes(sub, value, path, depth + 1))
+            {
+                self.issue(path, "does not match any allowed shape (anyOf)");
+            }
+        }
+        if let Some(one) = schema.get("oneOf").and_then(Value::as_array) {
+            let matches = one
+                .iter()
+                .filter(|sub| self.passes(sub, value, path, depth + 1))
+                .count();
+            if matches != 1 {
+                self.issue(
+                    path,
+                    format!("must match exactly one shape (oneOf), matched {matches}"),
+                );
+            }
+        }
+    }
+
+    fn check_object(
+        &mut self,
+        schema: &'a Map<String, Value>,
+        object: &Map<String, Value>,
+        path: &str,
+        depth: usize,
+    ) {
+        let properties = schema.get("properties").and_then(Value::as_object);
+        if let Some(required) = schema.get("required").and_then(Value::as_array) {
+            for key in required.iter().filter_map(Value::as_str) {
+                if !object.contains_key(key) {
+                    self.issue(&format!("{path}.{key}"), "is required");
+                }
+            }
+        }
+        for (key, item) in object {
+            let item_path = format!("{path}.{key}");
+            match properties.and_then(|properties| properties.get(key)) {
+                Some(sub) => self.check(sub, item, &item_path, depth + 1),
+                None => match schema.get("additionalProperties") {
+                    Some(Value::Bool(false)) => self.issue(&item_path, "is not allowed"),
+                    Some(sub @ Value::Object(_)) => self.check(sub, item, &item_path, depth + 1),
+                    _ => {}
+                },
+            }
+        }
+    }
+
+    fn check_array(
+        &mut self,
+        schema: &'a Map<String, Value>,
+        items: &[Value],
+        path: &str,
+        depth: usize,
+    ) {
+        let count = items.len() as u64;
+        if let Some(min) = schema.get("minItems").and_then(Value::as_u64) {
+            if count < min {
+                self.issue(path, format!("must have at least {min} items, got {count}"));
+            }
+        }
+        if let Some(max) = schema.get("maxItems").and_then(Value::as_u64) {
+            if count > max {
+                self.issue(path, format!("must have at most {max} items, got {count}"));
+            }
+        }
+        if let Some(sub) = schema.get("items") {
+            for (index, item) in items.iter().enumerate() {
+                self.check(sub, item, &format!("{path}[{index}]"), depth + 1);
+            }
+        }
+    }
+
+    fn check_string(&mut self, schema: &Map<String, Value>, text: &str, path: &str) {
+        let length = text.chars().count() as u64;
+        if let Some(min) = schema.get("minLength").and_then(Value::as_u64) {
+            if length < min {
+                self.issue(path, format!("must be at least {min} characters"));
+            }
+        }
+        if let Some(max) = schema.get("maxLength").and_then(Value::as_u64) {
+            if length > max {
+                self.issue(path, format!("must be at most {max} characters"));
+            }
+        }
+    }
+
+    fn check_number(&mut self, schema: &Map<String, Value>, number: f64, path: &str) {
+        let bound = |key: &str| schema.get(key).and_then(Value::as_f64);
+        if let Some(min) = bound("minimum").filter(|min| number < *min) {
+            self.issue(path, format!("must be >= {min}"));
+        }
+        if let Some(max) = bound("maximum").filter(|max| number > *max) {
+            self.issue(path, format!("must be <= {max}"));
+        }
+        if let Some(min) = bound("exclusiveMinimum").filter(|min| number <= *min) {
+            self.issue(path, format!("must be > {min}"));
+        }
+        if let Some(max) = bound("exclusiveMaximum").filter(|max| number >= *max) {
+            self.issue(path, format!("must be < {max}"));
+        }
+    }
+}
+
+// Collects `keyword at path` for every keyword the validator would skip.
+fn find_unsupported(schema: &Value, path: &str, found: &mut Vec<String>) {
+    let Some(schema) = schema.as_object() else {
+        return;
+    };
+    for (key, sub) in schema {
+        let sub_path = format!("{path}/{key}");
+        match key.as_str() {
+            "properties" | "$defs" | "definitions" => {
+                for (name, nested) in sub.as_object().into_iter().flatten() {
+                    find_unsupported(nested, &format!("{sub_path}/{name}"), found);
+                }
+            }
+            "allOf" | "anyOf" | "oneOf" => {
+                for (index, nested) in sub.as_array().into_iter().flatten().enumerate() {
+                    find_unsupported(nested, &format!("{sub_path}/{index}"), found);
+                }
+            }
+            // The tuple form of `items` is a different keyword in disguise.
+            "items" if sub.is_array() => found.push(format!("items (array form) at {sub_path}")),
+            "items" | "additionalProperties" => find_unsupported(sub, &sub_path, found),
+            key if CHECKED_KEYWORDS.contains(&key) || IGNORED_KEYWORDS.contains(&key) => {}
+            key => found.push(format!("{key} at {path}")),
+        }
+    }
+}
+
+/// Keywords in `schema` that `validate` can't check, as `keyword at #/pointer`.
+pub fn unsupported_keywords(schema: &Value) -> Vec<String> {
+    let mut found = Vec::new();
+    find_unsupported(schema, "#", &mut found);
+    found
+}
+
+/// Validates `value` against `schema`; an empty result means it matches.
+pub fn validate(schema: &Value, value: &Value) -> Vec<ValidationIssue> {
+    let mut validator = Validator {
+        root: schema,
+        issues: Vec::new(),
+    };
+    validator.check(schema, value, "$", 0);
+    validator.issues
+}
+
+// Models occasionally wrap JSON in a Markdown fence despite `format`.
+fn parse_output(content: &str) -> Result<Value, String> {
+    let trimmed = content.trim();
+    let unfenced = trimmed
+        .strip_prefix("```json")
+        .or_else(|| trimmed.strip_prefix("```"))
+        .and_then(|rest| rest.strip_suffix("```"))
+        .unwrap_or(trimmed);
+    serde_json::from_str(unfenced.trim()).map_err(|err| format!("reply is not valid JSON: {err}"))
+}
+
+fn describe(issues: &[ValidationIssue]) -> String {
+    issues
+        .iter()
+        .map(|issue| format!("{}: {}", issue.field, issue.message))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+// Sends `request` until the reply validates, feeding each mismatch back to the model.
+async fn chat_until_valid<F, Fut>(
+    mut request: ChatRequest,
+    schema: &Value,
+    attempts: u32,
+    mut send: F,
+) -> Result<StructuredChatResponse, ChatError>
+where
+    F: FnMut(ChatRequest) -> Fut,
+    Fut: Future<Output = Result<ChatResponse, ChatError>>,
+{
+    let mut issues = Vec::new();
+    for attempt in 1..=attempts {
+        let response = send(request.clone()).await?;
+        issues = match parse_output(&response.message.content) {
+            Ok(data) => {
+                let found = validate(schema, &data);
+                if found.is_empty() {
+                    return Ok(StructuredChatResponse {
+                        data,
+                        attempts: attempt,
+                        response,
+                    });
+                }
+                found
+            }
+            Err(err) => vec![ValidationIssue {
+                field: "$".into(),
+                message: err,
+            }],
+        };
+        eprintln!(
+            "Structured output attempt {attempt}/{attempts} failed: {}",
+            describe(&issues)
+        );
+        request.messages.push(response.message);
+        request.messages.push(ChatMessage::new(
+            ChatRole::User,
+            format!(
+                "Your previous reply did not match the required JSON schema: {}. \
+                 Reply again with only JSON that matches the schema.",
+                describe(&issues)
+            ),
+        ));
+    }
+
+    Err(ChatError::SchemaMismatch {
+        message: format!(
+            "Model output did not match the schema after {attempts} attempts: {}",
+            describe(&issues)
+        ),
+        issues,
+    })
+}
+
+#[tauri::command]
+pub async fn ollama_chat_structured(
+    app: AppHandle,
+    request: Value,
+    schema: Value,
+    max_attempts: Option<u32>,
+) -> Result<StructuredChatResponse, ChatError> {
+    if !schema.is_object() {
+        return Err("Schema must be a JSON object.".to_string().into());
+    }
+    let unsupported = unsupported_keywords(&schema);
+    if !unsupported.is_empty() {
+        return Err(format!(
+            "Schema uses keywords that can't be validated: {}",
+            unsupported.join(", ")
+        )
+        .into());
+    }
+    let mut request = ChatRequest::from_value(request, &config::load_overlay_config(&app).models)?;
+    request.format = Some(schema.clone());
+    request.stream = Some(false);
+    let attempts = max_attempts
+        .unwrap_or(DEFAULT_ATTEMPTS)
+        .clamp(1, MAX_ATTEMPTS);
+
+    chat_until_valid(request, &schema, attempts, |request| {
+        let app = app.clone();
+        async move { provider::send_chat(&app, &request).await }
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use copilot_client::models::ModelsConfig;
+    use serde_json::json;
+
+    fn schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "name": { "type": "string", "minLength": 1 },
+                "tags": { "type": "array", "items": { "$ref": "#/$defs/tag" }, "maxItems": 2 },
+                "score": { "type": "number", "minimum": 0, "maximum": 1 },
+            },
+            "required": ["name"],
+            "additionalProperties": false,
+            "$defs": { "tag": { "enum": ["a", "b"] } },
+        })
+    }
+
+    fn fields(issues: &[ValidationIssue]) -> Vec<&str> {
+        issues.iter().map(|issue| issue.field.as_str()).collect()
+    }
+
+    #[test]
+    fn accepts_matching_values() {
+        let value = json!({ "name": "x", "tags": ["a", "b"], "score": 0.5 });
+        assert!(validate(&schema(), &value).is_empty());
+    }
+
+    #[test]
+    fn reports_each_mismatch_with_its_path() {
+        let value = json!({ "tags": ["a", "c", "b"], "score": 2, "extra": true });
+        let issues = validate(&schema(), &value);
+        assert_eq!(
+            fields(&issues),
+            ["$.name", "$.extra", "$.score", "$.tags", "$.tags[1]"]
+        );
+        assert_eq!(issues[1].message, "is not allowed");
+        assert_eq!(issues[2].message, "must be <= 1");
+    }
+
+    #[test]
+    fn checks_combinators() {
+        let schema = json!({ "oneOf": [{ "type": "integer" }, { "type": "number" }] });
+        // 3 is both an integer and a number, so it matches two branches.
+        assert!(validate(&schema, &json!(1.5)).is_empty());
+        let issues = validate(&schema, &json!(3));
+        assert!(issues[0].message.contains("matched 2"), "{issues:?}");
+
+        let schema = json!({ "anyOf": [{ "type": "string" }, { "type": "null" }] });
+        assert!(validate(&schema, &json!(null)).is_empty());
+        assert_eq!(validate(&schema, &json!(1)).len(), 1);
+    }
+
+    #[test]
+    fn finds_unsupported_keywords() {
+        assert!(unsupported_keywords(&schema()).is_empty());
+        let schema = json!({
+            "title": "Contact",
+            "type": "object",
+            "properties": {
+                "email": { "type": "string", "format": "email" },
+                "phones": { "type": "array", "items": { "pattern": "^[0-9]+$" } },
+                "pair": { "items": [{ "type": "string" }] },
+            },
+            "if": { "required": ["email"] },
+        });
+        assert_eq!(
+            unsupported_keywords(&schema),
+            [
+                "if at #",
+                "format at #/properties/email",
+                "items (array form) at #/properties/pair/items",
+                "pattern at #/properties/phones/items",
+            ]
+        );
+    }
+
+    fn reply(content: &str) -> ChatResponse {
+        ChatResponse {
+            model: "llama3.2".into(),
+            created_at: None,
+            message: ChatMessage::new(ChatRole::Assistant, content),
+            done: true,
+            stats: Default::default(),
+            context: None,
+        }
+    }
+
+    // Replays `replies` in order and records the request behind each one.
+    fn run(
+        replies: &[&str],
+        attempts: u32,
+    ) -> (Result<StructuredChatResponse, ChatError>, Vec<ChatRequest>) {
+        let request = ChatRequest::from_value(
+            json!({ "model": "llama3.2", "messages": [{ "role": "user", "content": "Describe x" }] }),
+            &ModelsConfig::default(),
+        )
+        .unwrap();
+        let mut replies = replies.iter().map(|content| reply(content));
+        let mut sent = Vec::new();
+        let result = tauri::async_runtime::block_on(chat_until_valid(
+            request,
+            &schema(),
+            attempts,
+            |request| {
+                sent.push(request);
+                let next = replies.next().expect("no more scripted replies");
+                async move { Ok(next) }
+            },
+        ));
+        (result, sent)
+    }
+
+    #[test]
+    fn retries_with_the_mismatch_until_valid() {
+        let (result, sent) = run(&["{\"tags\": []}", "```json\n{\"name\": \"x\"}\n```"], 3);
+        let result = result.expect("second reply validates");
+        assert_eq!(result.attempts, 2);
+        assert_eq!(result.data, json!({ "name": "x" }));
+
+        // The retry carries the bad reply and a correction naming the field.
+        let retry = &sent[1].messages;
+        assert_eq!(retry.len(), 3);
+        assert_eq!(retry[1].content, "{\"tags\": []}");
+        assert!(
+            retry[2].content.contains("$.name: is required"),
+            "{}",
+            retry[2].content
+        );
+    }
+
+    #[test]
+    fn gives_up_after_the_last_attempt() {
+        let (result, sent) = run(&["not json", "{}"], 2);
+        assert_eq!(sent.len(), 2);
+        match result {
+            Err(ChatError::SchemaMismatch { issues, .. }) => {
+                assert_eq!(fields(&issues), ["$.name"]);
+            }
+            other => panic!("expected a schema mismatch, got {other:?}"),
+        }
+    }
+}
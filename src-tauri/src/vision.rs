@@ -0,0 +1,120 @@
+use copilot_client::chat::{ChatError, ChatRequest};
+use copilot_client::ollama::OllamaEndpoint;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::config;
+use crate::provider::Provider;
+
+// Sends turns with images to a vision-capable model. Text-only models reject
+// or silently ignore images, so the request is rerouted before it goes out.
+
+/// `/api/show` capabilities keyed by model name.
+#[derive(Default)]
+pub struct CapabilityCache {
+    models: Mutex<HashMap<String, Vec<String>>>,
+}
+
+/// Emitted as `ollama:model_routed` when a request is answered by another model.
+#[derive(Debug, Serialize, Clone)]
+struct ModelRoutedEvent {
+    stream_id: Option<String>,
+    requested_model: String,
+    model: String,
+}
+
+async fn capabilities(
+    app: &AppHandle,
+    endpoint: &OllamaEndpoint,
+    model: &str,
+) -> Option<Vec<String>> {
+    let cache = app.state::<CapabilityCache>();
+    if let Some(known) = cache
+        .models
+        .lock()
+        .ok()
+        .and_then(|models| models.get(model).cloned())
+    {
+        return Some(known);
+    }
+    // Not cached on failure, so a model pulled later is picked up.
+    let info = endpoint.show(model).await.ok()?;
+    if let Ok(mut models) = cache.models.lock() {
+        models.insert(model.to_string(), info.capabilities.clone());
+    }
+    Some(info.capabilities)
+}
+
+async fn has_vision(app: &AppHandle, endpoint: &OllamaEndpoint, model: &str) -> bool {
+    capabilities(app, endpoint, model)
+        .await
+        .is_some_and(|caps| caps.iter().any(|cap| cap == "vision"))
+}
+
+// The configured vision model if it is installed, else the first installed one.
+async fn find_vision_model(app: &AppHandle, endpoint: &OllamaEndpoint) -> Option<String> {
+    let configured = config::load_overlay_config(app)
+        .models
+        .default_vision_model
+        .filter(|model| !model.trim().is_empty());
+    if let Some(model) = configured {
+        if has_vision(app, endpoint, &model).await {
+            return Some(model);
+        }
+        eprintln!("Configured vision model {model} is not installed or lacks vision");
+    }
+    let installed = endpoint.list_models().await.ok()?;
+    for model in installed.models {
+        if has_vision(app, endpoint, &model.name).await {
+            return Some(model.name);
+        }
+    }
+    None
+}
+
+/// Switches an image-bearing request to a vision model when its own model
+/// can't see images. Only applies to Ollama providers.
+pub async fn route_images(
+    app: &AppHandle,
+    provider: &Provider,
+    request: &mut ChatRequest,
+    stream_id: Option<&str>,
+) -> Result<(), ChatError> {
+    let Provider::Ollama(endpoint) = provider else {
+        return Ok(());
+    };
+    if request
+        .messages
+        .iter()
+        .all(|message| message.images.is_empty())
+    {
+        return Ok(());
+    }
+    // Unknown models and servers too old to report capabilities are left to Ollama.
+    let Some(caps) = capabilities(app, endpoint, &request.model).await else {
+        return Ok(());
+    };
+    if caps.is_empty() || caps.iter().any(|cap| cap == "vision") {
+        return Ok(());
+    }
+    let Some(model) = find_vision_model(app, endpoint).await else {
+        return Err(format!(
+            "{} can't read images and no vision model is installed. \
+             Pull one (for example `ollama pull llava`) or set a default vision model in Preferences.",
+            request.model
+        )
+        .into());
+    };
+    eprintln!("Routing image request from {} to {model}", request.model);
+    let _ = app.emit(
+        "ollama:model_routed",
+        ModelRoutedEvent {
+            stream_id: stream_id.map(str::to_string),
+            requested_model: std::mem::replace(&mut request.model, model.clone()),
+            model,
+        },
+    );
+    Ok(())
+}
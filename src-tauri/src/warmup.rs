@@ -0,0 +1,109 @@
+use copilot_client::ollama::OllamaEndpoint;
+use serde_json::{json, Value};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Listener, Manager};
+
+use crate::config;
+use crate::provider::Provider;
+
+// Preloads the model when the overlay is shown, so the first prompt after an
+// idle period doesn't pay the load time. Only applies to Ollama providers.
+
+// Skip repeat preloads of the same model when the overlay is toggled quickly.
+const WARMUP_COOLDOWN: Duration = Duration::from_secs(60);
+
+#[derive(Default)]
+pub struct ModelWarmer {
+    // Most recent model used for chat; warmed when no model is configured.
+    last_model: Mutex<Option<String>>,
+    last_warmup: Mutex<Option<(String, Instant)>>,
+}
+
+/// Records the model of an outgoing chat request.
+pub fn remember_model(app: &AppHandle, model: &str) {
+    let warmer = app.state::<ModelWarmer>();
+    let mut last = warmer
+        .last_model
+        .lock()
+        .unwrap_or_else(|err| err.into_inner());
+    *last = Some(model.to_string());
+}
+
+fn ollama_endpoint(app: &AppHandle) -> Result<OllamaEndpoint, String> {
+    match Provider::resolve(app)? {
+        Provider::Ollama(endpoint) => Ok(endpoint),
+        _ => Err("Model loading is only controlled for Ollama providers.".into()),
+    }
+}
+
+async fn warm_up(app: AppHandle) {
+    let config = config::load_overlay_config(&app);
+    let warmup = config.warmup;
+    if !warmup.enabled {
+        return;
+    }
+    let warmer = app.state::<ModelWarmer>();
+    let model = warmup
+        .model
+        .filter(|model| !model.trim().is_empty())
+        .or_else(|| warmer.last_model.lock().ok()?.clone())
+        .or(config.models.default_chat_model);
+    let Some(model) = model else {
+        return;
+    };
+    {
+        let Ok(mut last) = warmer.last_warmup.lock() else {
+            return;
+        };
+        if let Some((previous, at)) = last.as_ref() {
+            if previous == &model && at.elapsed() < WARMUP_COOLDOWN {
+                return;
+            }
+        }
+        *last = Some((model.clone(), Instant::now()));
+    }
+    let Ok(endpoint) = ollama_endpoint(&app) else {
+        return;
+    };
+    if let Err(err) = endpoint
+        .load_model(&model, Value::String(warmup.keep_alive))
+        .await
+    {
+        eprintln!("Model warm-up failed for {model}: {err}");
+    }
+}
+
+/// Warms the model whenever the overlay is shown (toggle shortcut, tray or command).
+pub fn listen_for_overlay(app: &AppHandle) {
+    let handle = app.clone();
+    app.listen_any("overlay:shown", move |_| {
+        tauri::async_runtime::spawn(warm_up(handle.clone()));
+    });
+}
+
+/// Unloads `model`, or every loaded model when none is given, to free memory.
+#[tauri::command]
+pub async fn ollama_unload_model(
+    app: AppHandle,
+    model: Option<String>,
+) -> Result<Vec<String>, String> {
+    let endpoint = ollama_endpoint(&app)?;
+    let models = match model {
+        Some(model) => vec![model],
+        None => endpoint
+            .running_models()
+            .await?
+            .into_iter()
+            .map(|running| running.name)
+            .collect(),
+    };
+    for model in &models {
+        endpoint.load_model(model, json!(0)).await?;
+    }
+    // Let the next overlay show warm the model up again right away.
+    if let Ok(mut last) = app.state::<ModelWarmer>().last_warmup.lock() {
+        *last = None;
+    }
+    Ok(models)
+}
@@ -0,0 +1,460 @@
+use copilot_client::http::describe_reqwest_error;
+use futures_util::StreamExt;
+use reqwest::Url;
+use serde::Serialize;
+use std::time::Duration;
+use tauri::AppHandle;
+
+use crate::config;
+
+// `fetch_url`: downloads a page under size/time limits and reduces the HTML to
+// readable text. Links are kept as numbered references so the model can cite them.
+
+const MAX_DOWNLOAD_BYTES: usize = 2_000_000;
+const MAX_CONTENT_CHARS: usize = 20_000;
+const MAX_LINKS: usize = 100;
+const FETCH_TIMEOUT: Duration = Duration::from_secs(15);
+// Tags whose contents are never readable text. Forms and headers can wrap a
+// whole page (ASP.NET pages, article titles), so only the controls are skipped.
+const SKIPPED_TAGS: &[&str] = &[
+    "script", "style", "noscript", "template", "svg", "canvas", "iframe", "head", "nav", "footer",
+    "aside", "button", "select", "textarea",
+];
+// Tags that end a line of text.
+const BLOCK_TAGS: &[&str] = &[
+    "p",
+    "div",
+    "br",
+    "li",
+    "tr",
+    "section",
+    "article",
+    "main",
+    "blockquote",
+    "pre",
+    "table",
+    "ul",
+    "ol",
+    "dl",
+    "dt",
+    "dd",
+    "hr",
+    "figcaption",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+];
+// A `<main>`/`<article>` region shorter than this is probably not the page body.
+const MIN_MAIN_CHARS: usize = 200;
+
+#[derive(Debug, Serialize, Clone)]
+pub struct FetchedLink {
+    pub index: usize,
+    pub text: String,
+    pub url: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FetchUrlResponse {
+    pub url: String,
+    // After redirects.
+    pub final_url: String,
+    pub status: u16,
+    pub content_type: Option<String>,
+    pub title: Option<String>,
+    pub bytes: u64,
+    pub content: String,
+    pub links: Vec<FetchedLink>,
+    // Set when the download or the extracted text hit a limit.
+    pub truncated: bool,
+}
+
+fn decode_entity(entity: &str) -> Option<char> {
+    match entity {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" | "#39" => Some('\''),
+        "nbsp" => Some(' '),
+        _ => {
+            let code = entity.strip_prefix('#')?;
+            let value = match code.strip_prefix(['x', 'X']) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => code.parse().ok()?,
+            };
+            char::from_u32(value)
+        }
+    }
+}
+
+fn decode_entities(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let decoded = rest
+            .find(';')
+            .filter(|end| *end <= 10)
+            .and_then(|end| Some((decode_entity(&rest[1..end])?, end)));
+        match decoded {
+            Some((ch, end)) => {
+                out.push(ch);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+// Finds the `>` closing a tag, ignoring any inside quoted attribute values.
+fn tag_end(html: &str, from: usize) -> Option<usize> {
+    let mut quote = None;
+    for (offset, ch) in html[from..].char_indices() {
+        match (quote, ch) {
+            (Some(open), ch) if ch == open => quote = None,
+            (None, '"' | '\'') => quote = Some(ch),
+            (None, '>') => return Some(from + offset),
+            _ => {}
+        }
+    }
+    None
+}
+
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    let lower = tag.to_ascii_lowercase();
+    let mut search = 0;
+    while let Some(found) = lower[search..].find(name) {
+        let start = search + found;
+        search = start + name.len();
+        let preceded = lower[..start].ends_with(|ch: char| ch.is_whitespace());
+        let rest = lower[search..].trim_start();
+        if !preceded || !rest.starts_with('=') {
+            continue;
+        }
+        let value_start = tag.len() - rest.len() + 1;
+        let value = tag[value_start..].trim_start();
+        let (value, _) = match value.chars().next() {
+            Some(quote @ ('"' | '\'')) => value[1..].split_once(quote)?,
+            _ => value
+                .split_once(|ch: char| ch.is_whitespace() || ch == '>')
+                .unwrap_or((value, "")),
+        };
+        return Some(decode_entities(value));
+    }
+    None
+}
+
+struct Extractor<'a> {
+    base: &'a Url,
+    text: String,
+    links: Vec<FetchedLink>,
+    pending_space: bool,
+    // Link href and where its text started, while inside an `<a>`.
+    open_link: Option<(String, usize)>,
+}
+
+impl Extractor<'_> {
+    fn push_text(&mut self, raw: &str) {
+        if raw.is_empty() {
+            return;
+        }
+        let decoded = decode_entities(raw);
+        // Inline tags split words without whitespace, e.g. `<b>world</b>,`.
+        let mut space = self.pending_space || decoded.starts_with(char::is_whitespace);
+        for word in decoded.split_whitespace() {
+            if space && !self.text.is_empty() && !self.text.ends_with(['\n', ' ']) {
+                self.text.push(' ');
+            }
+            self.text.push_str(word);
+            space = true;
+        }
+        self.pending_space = decoded.ends_with(char::is_whitespace);
+    }
+
+    fn break_line(&mut self) {
+        self.pending_space = false;
+        let trimmed = self.text.trim_end_matches(' ').len();
+        self.text.truncate(trimmed);
+        if !self.text.is_empty() && !self.text.ends_with("\n\n") {
+            self.text.push('\n');
+        }
+    }
+
+    fn close_link(&mut self) {
+        let Some((href, start)) = self.open_link.take() else {
+            return;
+        };
+        // A block break inside the link can trim text from before `start`.
+        let text = self.text.get(start..).unwrap_or("").trim().to_string();
+        let Ok(url) = self.base.join(&href) else {
+            return;
+        };
+        if !matches!(url.scheme(), "http" | "https") || self.links.len() >= MAX_LINKS {
+            return;
+        }
+        let index = self.links.len() + 1;
+        self.text.push_str(&format!(" [{index}]"));
+        self.links.push(FetchedLink {
+            index,
+            text,
+            url: url.to_string(),
+        });
+    }
+
+    fn run(&mut self, html: &str) {
+        let lower = html.to_ascii_lowercase();
+        let mut pos = 0;
+        while let Some(found) = html[pos..].find('<') {
+            let start = pos + found;
+            self.push_text(&html[pos..start]);
+            if lower[start..].starts_with("<!--") {
+                pos = lower[start..]
+                    .find("-->")
+                    .map_or(html.len(), |end| start + end + 3);
+                continue;
+            }
+            let Some(end) = tag_end(html, start) else {
+                break;
+            };
+            let tag = &html[start + 1..end];
+            pos = end + 1;
+            let closing = tag.starts_with('/');
+            let name = tag
+                .trim_start_matches('/')
+                .split(|ch: char| ch.is_whitespace() || ch == '/')
+                .next()
+                .unwrap_or("")
+                .to_ascii_lowercase();
+
+            if !closing && SKIPPED_TAGS.contains(&name.as_str()) && !tag.ends_with('/') {
+                let close = format!("</{name}");
+                pos = match lower[pos..].find(&close) {
+                    Some(offset) => tag_end(html, pos + offset).map_or(html.len(), |i| i + 1),
+                    None => html.len(),
+                };
+                continue;
+            }
+            match (name.as_str(), closing) {
+                ("a", false) => {
+                    self.close_link();
+                    if let Some(href) = attribute(tag, "href") {
+                        self.open_link = Some((href, self.text.len()));
+                    }
+                }
+                ("a", true) => self.close_link(),
+                ("li", false) => {
+                    self.break_line();
+                    self.text.push_str("- ");
+                }
+                (name, _) if BLOCK_TAGS.contains(&name) => self.break_line(),
+                _ => {}
+            }
+        }
+        if pos < html.len() {
+            self.push_text(&html[pos..]);
+        }
+        self.close_link();
+    }
+}
+
+// The inner HTML of the first `<main>` or `<article>`, when the page has one.
+fn main_region(html: &str) -> Option<&str> {
+    let lower = html.to_ascii_lowercase();
+    ["main", "article"].iter().find_map(|name| {
+        let open = lower.find(&format!("<{name}"))?;
+        let body_start = tag_end(html, open)? + 1;
+        let close = lower.rfind(&format!("</{name}"))?;
+        (close > body_start).then(|| &html[body_start..close])
+    })
+}
+
+fn page_title(html: &str) -> Option<String> {
+    let lower = html.to_ascii_lowercase();
+    let open = lower.find("<title")?;
+    let start = tag_end(html, open)? + 1;
+    let end = start + lower[start..].find("</title")?;
+    let title = decode_entities(&html[start..end]);
+    let title = title.split_whitespace().collect::<Vec<_>>().join(" ");
+    (!title.is_empty()).then_some(title)
+}
+
+fn extract(html: &str, base: &Url) -> (Option<String>, String, Vec<FetchedLink>) {
+    let run = |fragment: &str| {
+        let mut extractor = Extractor {
+            base,
+            text: String::new(),
+            links: Vec::new(),
+            pending_space: false,
+            open_link: None,
+        };
+        extractor.run(fragment);
+        extractor
+    };
+    let page = run(html);
+    let chosen = match main_region(html).map(run) {
+        Some(main) if main.text.trim().chars().count() >= MIN_MAIN_CHARS => main,
+        _ => page,
+    };
+    (
+        page_title(html),
+        chosen.text.trim().to_string(),
+        chosen.links,
+    )
+}
+
+/// Downloads and extracts one page; shared by the command and the agent tool.
+pub async fn fetch_readable(url: &str) -> Result<FetchUrlResponse, String> {
+    let parsed = Url::parse(url.trim()).map_err(|err| format!("Invalid URL: {err}"))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err("Only http and https URLs can be fetched.".into());
+    }
+    let client = reqwest::Client::builder()
+        .timeout(FETCH_TIMEOUT)
+        .redirect(reqwest::redirect::Policy::limited(5))
+        .build()
+        .map_err(|err| format!("HTTP client error: {err}"))?;
+    let host = parsed.host_str().unwrap_or("the server").to_string();
+    let response = client
+        .get(parsed.clone())
+        .send()
+        .await
+        .map_err(|err| describe_reqwest_error(&err, &host))?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(format!("Fetch failed with HTTP {status}"));
+    }
+    let final_url = response.url().clone();
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let is_html = content_type
+        .as_deref()
+        .is_none_or(|value| value.contains("html"));
+    let is_text = content_type
+        .as_deref()
+        .is_some_and(|value| value.starts_with("text/") || value.contains("json"));
+    if !is_html && !is_text {
+        return Err(format!(
+            "Unsupported content type: {}",
+            content_type.as_deref().unwrap_or("unknown")
+        ));
+    }
+
+    let mut body = Vec::new();
+    let mut truncated = false;
+    let mut stream = response.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(|err| describe_reqwest_error(&err, &host))?;
+        let room = MAX_DOWNLOAD_BYTES - body.len();
+        if chunk.len() > room {
+            body.extend_from_slice(&chunk[..room]);
+            truncated = true;
+            break;
+        }
+        body.extend_from_slice(&chunk);
+    }
+    let raw = String::from_utf8_lossy(&body);
+    let (title, mut content, links) = if is_html {
+        extract(&raw, &final_url)
+    } else {
+        (None, raw.trim().to_string(), Vec::new())
+    };
+    if content.chars().count() > MAX_CONTENT_CHARS {
+        content = content.chars().take(MAX_CONTENT_CHARS).collect();
+        truncated = true;
+    }
+
+    Ok(FetchUrlResponse {
+        url: parsed.to_string(),
+        final_url: final_url.to_string(),
+        status: status.as_u16(),
+        content_type,
+        title,
+        bytes: body.len() as u64,
+        content,
+        links,
+        truncated,
+    })
+}
+
+#[tauri::command]
+pub async fn fetch_url(app: AppHandle, url: String) -> Result<FetchUrlResponse, String> {
+    if !config::tool_enabled(&config::load_overlay_config(&app), "fetch_url") {
+        return Err("URL fetching is disabled in settings.".into());
+    }
+    fetch_readable(&url)
+        .await
+        .inspect_err(|err| eprintln!("fetch_url failed for {url}: {err}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn extract_text(html: &str) -> String {
+        let base = Url::parse("https://example.com/docs/").unwrap();
+        extract(html, &base).1
+    }
+
+    #[test]
+    fn skips_script_and_style_contents() {
+        let text = extract_text(
+            "<style>p { color: red }</style><p>Visible</p>\
+             <script>if (a < b) { alert('</p>'); }</script><noscript>Enable JS</noscript>",
+        );
+        assert_eq!(text, "Visible");
+    }
+
+    #[test]
+    fn block_tags_break_lines_and_inline_tags_do_not() {
+        let text = extract_text(
+            "<h1>Title</h1><p>Hello <b>world</b>,<br>again</p><ul><li>one<li>two</ul>",
+        );
+        assert_eq!(text, "Title\n\nHello world,\nagain\n\n- one\n- two");
+    }
+
+    #[test]
+    fn decodes_named_and_numeric_entities() {
+        assert_eq!(
+            decode_entities("a &lt;b&gt; &#x41;&#66; &amp;&nbsp;&bogus; & c"),
+            "a <b> AB & &bogus; & c"
+        );
+        let base = Url::parse("https://example.com/").unwrap();
+        let (title, _, _) = extract("<title> Fish &amp;\n Chips </title>", &base);
+        assert_eq!(title.as_deref(), Some("Fish & Chips"));
+    }
+
+    #[test]
+    fn keeps_text_inside_forms_and_headers() {
+        let text = extract_text(
+            "<form action=\"/post\"><header><h2>Article title</h2></header>\
+             <p>Body text</p><button>Send</button><textarea>draft</textarea></form>",
+        );
+        assert_eq!(text, "Article title\n\nBody text");
+    }
+
+    #[test]
+    fn numbers_links_and_resolves_them_against_the_page() {
+        let base = Url::parse("https://example.com/docs/").unwrap();
+        let (_, text, links) = extract(
+            "<p>See <a href=\"intro.html\">the intro</a> or <a href=\"mailto:x@y\">mail</a>.</p>",
+            &base,
+        );
+        assert_eq!(text, "See the intro [1] or mail.");
+        assert_eq!(links.len(), 1);
+        assert_eq!(links[0].url, "https://example.com/docs/intro.html");
+        assert_eq!(links[0].text, "the intro");
+    }
+}
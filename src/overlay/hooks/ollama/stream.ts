@@ -1,4 +1,4 @@
-import { listen, type Event } from "@tauri-apps/api/event";
+import { Channel } from "@tauri-apps/api/core";
 import type { Dispatch, MutableRefObject, SetStateAction } from "react";
 import type { Message } from "ollama";
 
@@ -19,6 +19,8 @@ type StreamBindings = {
   model: string;
   toolConfig?: unknown;
   requestIdRef: MutableRefObject<number>;
+  // Backend id of the stream in flight, so the caller can cancel it.
+  activeStreamIdRef?: MutableRefObject<string | null>;
   streamMessageIdRef: MutableRefObject<number>;
   toolActivityRef?: MutableRefObject<string[] | null>;
   setMessages: Dispatch<SetStateAction<ChatMessage[]>>;
@@ -53,6 +55,7 @@ async function streamOllamaChat({
   model,
   toolConfig,
   requestIdRef,
+  activeStreamIdRef,
   streamMessageIdRef,
   toolActivityRef,
   setMessages,
@@ -130,15 +133,51 @@ async function streamOllamaChat({
   };
 
   const result = await new Promise<StreamResult | null>((resolve, reject) => {
-    let unlisten: (() => void) | null = null;
-    let active = true;
+    const channel = new Channel<StreamPayload>();
 
     const cleanup = () => {
-      active = false;
-      if (unlisten) unlisten();
+      channel.onmessage = () => {};
+      if (activeStreamIdRef?.current === streamId) {
+        activeStreamIdRef.current = null;
+      }
     };
 
-    const handler = (event: Event<StreamPayload>) => {
+    const finishAssistantMessage = (): StreamResult => {
+      ensureStreamingMessage();
+
+      const assistantMessage: AssistantPayload = {
+        role: "assistant",
+        ...(latestMessage ?? {}),
+        content,
+      };
+
+      const normalizedReasoning = normalizeThinkingValue(streamedReasoning);
+      if (normalizedReasoning && !assistantMessage.reasoning) {
+        assistantMessage.reasoning = normalizedReasoning;
+      }
+
+      const normalizedThinking = normalizeThinkingValue(streamedThinking);
+      if (normalizedThinking && !assistantMessage.thinking) {
+        assistantMessage.thinking = normalizedThinking;
+      }
+
+      const normalizedThoughts = normalizeThinkingValue(streamedThoughts);
+      if (normalizedThoughts && !assistantMessage.thoughts) {
+        assistantMessage.thoughts = normalizedThoughts;
+      }
+
+      if (reasoningStartedAt) {
+        const endAt = reasoningEndedAt ?? reasoningLastAt ?? Date.now();
+        thinkingDurationMs = Math.max(0, endAt - reasoningStartedAt);
+      }
+      return {
+        assistantMessage,
+        streamMessageId: streamedMessageId ?? undefined,
+        thinkingDurationMs,
+      };
+    };
+
+    const handler = (payload: StreamPayload) => {
       if (finished) return;
 
       if (requestId !== requestIdRef.current) {
@@ -149,7 +188,6 @@ async function streamOllamaChat({
         return;
       }
 
-      const payload = event.payload;
       if (payload.stream_id !== streamId) return;
 
       if (payload.error) {
@@ -160,6 +198,18 @@ async function streamOllamaChat({
         return;
       }
 
+      // Stopped from the backend (e.g. the stop shortcut); keep what arrived.
+      if (payload.kind === "cancelled") {
+        finished = true;
+        cleanup();
+        if (!content && streamedMessageId === null) {
+          resolve(null);
+          return;
+        }
+        resolve({ ...finishAssistantMessage(), cancelled: true });
+        return;
+      }
+
       const chunk = payload.chunk;
       if (!chunk) return;
 
@@ -227,62 +277,25 @@ async function streamOllamaChat({
       if (chunk.done) {
         finished = true;
         cleanup();
-        ensureStreamingMessage();
-
-        const assistantMessage: AssistantPayload = {
-          role: "assistant",
-          ...(latestMessage ?? {}),
-          content,
-        };
-
-        const normalizedReasoning = normalizeThinkingValue(streamedReasoning);
-        if (normalizedReasoning && !assistantMessage.reasoning) {
-          assistantMessage.reasoning = normalizedReasoning;
-        }
-
-        const normalizedThinking = normalizeThinkingValue(streamedThinking);
-        if (normalizedThinking && !assistantMessage.thinking) {
-          assistantMessage.thinking = normalizedThinking;
-        }
-
-        const normalizedThoughts = normalizeThinkingValue(streamedThoughts);
-        if (normalizedThoughts && !assistantMessage.thoughts) {
-          assistantMessage.thoughts = normalizedThoughts;
-        }
-
-        if (reasoningStartedAt) {
-          const endAt = reasoningEndedAt ?? reasoningLastAt ?? Date.now();
-          thinkingDurationMs = Math.max(0, endAt - reasoningStartedAt);
-        }
-        resolve({
-          assistantMessage,
-          streamMessageId: streamedMessageId ?? undefined,
-          thinkingDurationMs,
-        });
+        resolve(finishAssistantMessage());
       }
     };
 
-    listen<StreamPayload>("ollama:chunk", handler)
-      .then((unsubscribe) => {
-        if (!active) {
-          unsubscribe();
-          return;
-        }
-        unlisten = unsubscribe;
-        return ollamaChatStream(
-          {
-            model: resolvedModel,
-            messages: baseMessages,
-            tools: toolConfig,
-          },
-          streamId,
-        );
-      })
-      .catch((err) => {
-        finished = true;
-        cleanup();
-        reject(err);
-      });
+    channel.onmessage = handler;
+    if (activeStreamIdRef) activeStreamIdRef.current = streamId;
+    ollamaChatStream(
+      {
+        model: resolvedModel,
+        messages: baseMessages,
+        tools: toolConfig,
+      },
+      streamId,
+      channel,
+    ).catch((err) => {
+      finished = true;
+      cleanup();
+      reject(err);
+    });
   });
 
   return result;
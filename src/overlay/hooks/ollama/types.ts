@@ -44,11 +44,37 @@ export type AssistantPayload = Message & {
 
 export type StreamPayload = {
   stream_id: string;
+  kind?: "started" | "delta" | "done" | "error" | "cancelled";
   chunk?: {
     done?: boolean;
     message?: AssistantPayload;
   };
   error?: string;
+  stats?: {
+    done_reason?: string | null;
+    total_duration?: number | null;
+    load_duration?: number | null;
+    prompt_eval_count?: number | null;
+    prompt_eval_duration?: number | null;
+    eval_count?: number | null;
+    eval_duration?: number | null;
+  } | null;
+  // Derived by the backend; timings in milliseconds.
+  metrics?: {
+    model: string;
+    prompt_tokens?: number | null;
+    completion_tokens?: number | null;
+    load_ms?: number | null;
+    tokens_per_second?: number | null;
+    time_to_first_token_ms?: number | null;
+  } | null;
+  // Present when the backend checked the request against the context window.
+  context?: {
+    context_length: number;
+    estimated_tokens: number;
+    dropped_messages: number;
+    dropped_tokens: number;
+  } | null;
 };
 
 export type StreamResult = {
@@ -56,4 +82,6 @@ export type StreamResult = {
   toolCalls?: NonNullable<Message["tool_calls"]>;
   streamMessageId?: number;
   thinkingDurationMs?: number;
+  // Stopped before completion; `assistantMessage` holds the partial reply.
+  cancelled?: boolean;
 };
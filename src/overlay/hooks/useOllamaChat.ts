@@ -20,6 +20,7 @@ import {
   type ToolUsage,
 } from "./ollama";
 import { appendScreenshotMessage } from "./ollama/screenshot";
+import { cancelChatStream } from "../ollama/client";
 
 const OLLAMA_INSTRUCTIONS =
   "You are a fast, minimal desktop assistant. " +
@@ -41,6 +42,7 @@ export function useOllamaChat(model: string, options?: ToolOptions) {
   });
   // Used to cancel late responses when a new request is fired.
   const requestIdRef = useRef(0);
+  const activeStreamIdRef = useRef<string | null>(null);
   const streamMessageIdRef = useRef(0);
   const historyRef = useRef<Message[]>([]);
   const toolActivityRef = useRef<string[] | null>(null);
@@ -50,6 +52,7 @@ export function useOllamaChat(model: string, options?: ToolOptions) {
     model,
     toolConfig,
     requestIdRef,
+    activeStreamIdRef,
     streamMessageIdRef,
     toolActivityRef,
     setMessages,
@@ -297,6 +300,12 @@ export function useOllamaChat(model: string, options?: ToolOptions) {
   const cancelSend = () => {
     if (!isSending) return;
     requestIdRef.current += 1;
+    // Stop the model too, not just the UI.
+    const streamId = activeStreamIdRef.current;
+    activeStreamIdRef.current = null;
+    if (streamId) {
+      cancelChatStream(streamId).catch(() => {});
+    }
     setIsSending(false);
     setError(null);
     setToolUsage((prev) => ({ ...prev, inProgress: false }));
@@ -8,9 +8,13 @@ export function useOllamaHealth() {
   const [error, setError] = useState<string | null>(null);
   const [isChecking, setIsChecking] = useState(false);
 
-  // Backend emits this event when startup health check fails.
+  // Backend monitor emits this whenever Ollama goes down or comes back up.
   useTauriEvent<{ ok: boolean; error?: string }>("ollama:health", (event) => {
-    if (event.payload?.ok) return;
+    if (event.payload?.ok) {
+      setError(null);
+      setIsOpen(false);
+      return;
+    }
     setError(event.payload?.error ?? "Ollama unreachable.");
     setIsOpen(true);
   });
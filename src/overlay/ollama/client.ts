@@ -1,10 +1,11 @@
-import { invoke } from "@tauri-apps/api/core";
+import { invoke, type Channel } from "@tauri-apps/api/core";
 import type { Message } from "ollama";
 
 export type OllamaChatRequest = {
   model: string;
   messages: Message[];
   tools?: unknown;
+  think?: boolean | "low" | "medium" | "high";
 };
 
 export type OllamaChatResponse = {
@@ -22,12 +23,14 @@ export async function ollamaChat(request: OllamaChatRequest) {
     stream: false,
   };
   if (request.tools) payload.tools = request.tools;
+  if (request.think !== undefined) payload.think = request.think;
   return invoke<OllamaChatResponse>("ollama_chat", { request: payload });       
 }
 
-export async function ollamaChatStream(
+export async function ollamaChatStream<T>(
   request: OllamaChatRequest,
   streamId: string,
+  onEvent: Channel<T>,
 ) {
   const payload: Record<string, unknown> = {
     model: request.model,
@@ -35,5 +38,10 @@ export async function ollamaChatStream(
     stream: true,
   };
   if (request.tools) payload.tools = request.tools;
-  return invoke("ollama_chat_stream", { request: payload, streamId });
+  if (request.think !== undefined) payload.think = request.think;
+  return invoke("ollama_chat_stream", { request: payload, streamId, onEvent });
+}
+
+export async function cancelChatStream(streamId: string) {
+  return invoke<boolean>("cancel_chat_stream", { streamId });
 }
@@ -7,6 +7,27 @@ export const OVERLAY_CORNERS = [
 
 export type OverlayCorner = (typeof OVERLAY_CORNERS)[number];
 
+// Generation defaults for one model; mirrors Rust's ModelPreset.
+export type ModelPreset = {
+  temperature?: number | null;
+  top_p?: number | null;
+  num_ctx?: number | null;
+  num_thread?: number | null;
+  seed?: number | null;
+  stop?: string[] | null;
+  think?: boolean | "low" | "medium" | "high" | null;
+  system_prompt?: string | null;
+};
+
+// Chat backend profile; mirrors Rust's ProviderProfile. "ollama" is built in.
+export type ProviderProfile = {
+  id: string;
+  name: string;
+  kind: "ollama" | "openai_compatible";
+  base_url: string;
+  verify_tls: boolean;
+};
+
 // Persisted overlay settings shared between the UI and Tauri backend.
 export type OverlayConfig = {
   corner: OverlayCorner;
@@ -25,6 +46,44 @@ export type OverlayConfig = {
     web_search_enabled: boolean;
     agents_sdk_enabled: boolean;
     tool_toggles: Record<string, boolean>;
+    web_search_backend: "ollama" | "searxng";
+    searxng_url: string;
+  };
+  endpoint: {
+    base_url: string;
+    use_auth_header: boolean;
+    verify_tls: boolean;
+  };
+  providers: {
+    active_profile: string;
+    profiles: ProviderProfile[];
+  };
+  local_server: {
+    managed: boolean;
+    host: string;
+  };
+  scheduler: {
+    max_concurrent: number;
+  };
+  warmup: {
+    enabled: boolean;
+    keep_alive: string;
+    model?: string | null;
+  };
+  models: {
+    default_chat_model?: string | null;
+    default_vision_model?: string | null;
+    presets: Record<string, ModelPreset>;
+  };
+  knowledge: {
+    folders: string[];
+    embedding_model: string;
+    chunk_chars: number;
+    chunk_overlap: number;
+  };
+  streaming: {
+    legacy_events: boolean;
+    batch_ms: number;
   };
 };
 
@@ -50,5 +109,43 @@ export const DEFAULT_OVERLAY_CONFIG: OverlayConfig = {
     web_search_enabled: false,
     agents_sdk_enabled: false,
     tool_toggles: {},
+    web_search_backend: "ollama",
+    searxng_url: "",
+  },
+  endpoint: {
+    base_url: "http://localhost:11434",
+    use_auth_header: false,
+    verify_tls: true,
+  },
+  providers: {
+    active_profile: "ollama",
+    profiles: [],
+  },
+  local_server: {
+    managed: false,
+    host: "127.0.0.1:11434",
+  },
+  scheduler: {
+    max_concurrent: 1,
+  },
+  warmup: {
+    enabled: true,
+    keep_alive: "30m",
+    model: null,
+  },
+  models: {
+    default_chat_model: null,
+    default_vision_model: null,
+    presets: {},
+  },
+  knowledge: {
+    folders: [],
+    embedding_model: "nomic-embed-text",
+    chunk_chars: 1200,
+    chunk_overlap: 200,
+  },
+  streaming: {
+    legacy_events: false,
+    batch_ms: 24,
   },
 };